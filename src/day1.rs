@@ -1,22 +1,125 @@
-use aoc_2023_rust::Puzzle;
-use std::collections::HashMap;
+use aoc_2023_rust::matcher::{Match, Matcher};
+use aoc_2023_rust::parse::{comma_separated, key_value, parse_all, unsigned};
+use aoc_2023_rust::{Context, Puzzle};
+use nom::bytes::complete::is_not;
+use std::cmp::Reverse;
 
 #[derive(Debug, Clone)]
 pub struct Day1 {
     input: Vec<&'static str>,
+    context: Context,
 }
 
+const DIGITS: &[(&str, usize)] = &[
+    ("0", 0),
+    ("1", 1),
+    ("2", 2),
+    ("3", 3),
+    ("4", 4),
+    ("5", 5),
+    ("6", 6),
+    ("7", 7),
+    ("8", 8),
+    ("9", 9),
+];
+
+const WORDS: &[(&str, usize)] = &[
+    ("one", 1),
+    ("two", 2),
+    ("three", 3),
+    ("four", 4),
+    ("five", 5),
+    ("six", 6),
+    ("seven", 7),
+    ("eight", 8),
+    ("nine", 9),
+];
+
 impl Day1 {
     pub fn new() -> Day1 {
-        Day1 { input: Vec::new() }
+        Day1 {
+            input: Vec::new(),
+            context: Context::default(),
+        }
     }
 
     pub fn _clear(&mut self) {
         self.input = Vec::new()
     }
+
+    // The digits and number words part 2 looks for. The words can be given at
+    // runtime as e.g. `--param words=un:1,deux:2,trois:3` in place of the
+    // English ones, and `--param spelling=reversed` looks for them spelled
+    // backwards, as in `owt`.
+    fn vocabulary(&self) -> Matcher<usize> {
+        let words = match self.context.param("words") {
+            Some(list) => {
+                let word = is_not(": ,");
+                match parse_all(list, comma_separated(key_value(word, unsigned::<usize>))) {
+                    Ok(words) => words,
+                    Err(e) => panic!("Bad word list: {}!", e),
+                }
+            }
+            None => WORDS.to_vec(),
+        };
+        if let Some((word, value)) = words.iter().find(|(_, value)| *value > 9) {
+            panic!(
+                "The word {} stands for {}, which is not a digit!",
+                word, value
+            );
+        }
+        let vocabulary = Matcher::new(DIGITS.iter().copied().chain(words));
+        match self.context.param("spelling").unwrap_or("forward") {
+            "forward" => vocabulary,
+            "reversed" => vocabulary.reversed(),
+            s => panic!("Unknown spelling {}!", s),
+        }
+    }
+
+    fn total(&self, vocabulary: &Matcher<usize>) -> String {
+        let mut n = 0;
+        for (i, line) in self.input.iter().enumerate() {
+            match get_calibration(vocabulary, line) {
+                Some(calibration) => n += calibration.value(),
+                None => return format!("line {} has no digit: {:?}", i + 1, line),
+            }
+        }
+        format!("{:}", n)
+    }
+
+    fn table(&self, title: &str, vocabulary: &Matcher<usize>) -> Vec<String> {
+        let mut rows = vec![
+            format!("{} calibrations:", title),
+            format!(
+                "{:>5} | {:>4} {:<7} | {:>4} {:<7} | {:>5} | text",
+                "line", "pos", "first", "pos", "last", "value"
+            ),
+        ];
+        for (i, line) in self.input.iter().enumerate() {
+            let row = match get_calibration(vocabulary, line) {
+                Some(calibration) => format!(
+                    "{:>5} | {:>4} {:<7} | {:>4} {:<7} | {:>5} | {}",
+                    i + 1,
+                    calibration.first.start,
+                    calibration.first.pattern,
+                    calibration.last.start,
+                    calibration.last.pattern,
+                    calibration.value(),
+                    line
+                ),
+                None => format!("{:>5} | {:^34} | {}", i + 1, "no digit", line),
+            };
+            rows.push(row);
+        }
+        rows
+    }
 }
 
 impl Puzzle for Day1 {
+    fn configure(&mut self, context: &Context) {
+        self.context = context.clone();
+    }
+
     fn load_input(&mut self) {
         const INPUT: &str = include_str!("../inputs/1.input");
         for line in INPUT.lines() {
@@ -25,80 +128,87 @@ impl Puzzle for Day1 {
     }
 
     fn part1(&self) -> String {
-        let mapp: &[(&str, usize)] = &[
-            ("0", 0),
-            ("1", 1),
-            ("2", 2),
-            ("3", 3),
-            ("4", 4),
-            ("5", 5),
-            ("6", 6),
-            ("7", 7),
-            ("8", 8),
-            ("9", 9),
-        ];
-
-        let mut mapping: HashMap<&str, usize> = HashMap::new();
-        for (a, b) in mapp {
-            mapping.insert(a, *b);
-        }
-        let n = self
-            .input
-            .iter()
-            .fold(0, |acc, line| acc + get_calibration(&mapping, line));
-        format!("{:}", n)
+        self.total(&Matcher::new(DIGITS.iter().copied()))
     }
 
     fn part2(&self) -> String {
-        let mapp: &[(&str, usize)] = &[
-            ("0", 0),
-            ("1", 1),
-            ("2", 2),
-            ("3", 3),
-            ("4", 4),
-            ("5", 5),
-            ("6", 6),
-            ("7", 7),
-            ("8", 8),
-            ("9", 9),
-            ("one", 1),
-            ("two", 2),
-            ("three", 3),
-            ("four", 4),
-            ("five", 5),
-            ("six", 6),
-            ("seven", 7),
-            ("eight", 8),
-            ("nine", 9),
-        ];
+        self.total(&self.vocabulary())
+    }
 
-        let mut mapping: HashMap<&str, usize> = HashMap::new();
-        for (a, b) in mapp {
-            mapping.insert(a, *b);
+    fn details(&self) -> Vec<String> {
+        if !self.context.details {
+            return Vec::new();
         }
-        let n = self
-            .input
-            .iter()
-            .fold(0, |acc, line| acc + get_calibration(&mapping, line));
-        format!("{:}", n)
+        let mut rows = self.table("part 1", &Matcher::new(DIGITS.iter().copied()));
+        rows.extend(self.table("part 2", &self.vocabulary()));
+        rows
     }
 }
 
-fn get_calibration(m: &HashMap<&str, usize>, s: &str) -> usize {
-    let mut first = (s.len(), "");
-    let mut last = (0, "");
-    for p in m.keys() {
-        let v: Vec<(usize, &str)> = s.match_indices(p).collect();
-        if !v.is_empty() {
-            let (pos1, _) = v[0];
-            let (pos2, _) = v[v.len() - 1];
-            if pos1 <= first.0 {
-                first = (pos1, p)
-            }
-            if pos2 >= last.0 {
-                last = (pos2, p)
-            }
-        }
+// The matches that give a line its first and last digit.
+#[derive(Debug, Clone, Copy)]
+struct Calibration<'a> {
+    first: Match<'a, usize>,
+    last: Match<'a, usize>,
+}
+
+impl Calibration<'_> {
+    fn value(&self) -> usize {
+        self.first.value * 10 + self.last.value
+    }
+}
+
+// Words may overlap, as in "twone", so the first digit is the match that
+// starts first and the last digit the one that starts last.
+fn get_calibration<'a>(m: &'a Matcher<usize>, s: &str) -> Option<Calibration<'a>> {
+    let matches = m.find_all(s);
+    let first = *matches.iter().min_by_key(|m| (m.start, Reverse(m.end)))?;
+    let last = *matches.iter().max_by_key(|m| (m.start, m.end))?;
+    Some(Calibration { first, last })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(vocabulary: &Matcher<usize>, line: &str) -> Option<usize> {
+        get_calibration(vocabulary, line).map(|calibration| calibration.value())
+    }
+
+    #[test]
+    fn overlapping_words() {
+        let day = Day1::new();
+        let vocabulary = day.vocabulary();
+        assert_eq!(value(&vocabulary, "twone"), Some(21));
+        assert_eq!(value(&vocabulary, "eightwothree"), Some(83));
+        assert_eq!(value(&vocabulary, "xoneight"), Some(18));
+        assert_eq!(value(&vocabulary, "7pqrstsixteen"), Some(76));
+    }
+
+    #[test]
+    fn lines_without_digits() {
+        let mut day = Day1::new();
+        day.input = vec!["a1b", "twoxthree", "abc"];
+        let digits = Matcher::new(DIGITS.iter().copied());
+        assert_eq!(value(&digits, "twoxthree"), None);
+        assert_eq!(day.total(&digits), "line 2 has no digit: \"twoxthree\"");
+        assert_eq!(day.total(&day.vocabulary()), "line 3 has no digit: \"abc\"");
+    }
+
+    #[test]
+    fn other_vocabularies() {
+        let mut day = Day1::new();
+        let mut context = Context::default();
+        context
+            .params
+            .insert("words".to_string(), "un:1, deux:2, trois:3".to_string());
+        day.configure(&context);
+        assert_eq!(value(&day.vocabulary(), "xtroisdeuxunz"), Some(31));
+        assert_eq!(value(&day.vocabulary(), "one"), None);
+        context
+            .params
+            .insert("spelling".to_string(), "reversed".to_string());
+        day.configure(&context);
+        assert_eq!(value(&day.vocabulary(), "siort5xued"), Some(32));
     }
-    m[first.1] * 10 + m[last.1]
 }
@@ -1,23 +1,58 @@
 use crate::lib::Grid;
-use aoc_2023_rust::Puzzle;
+use aoc_2023_rust::{Context, Puzzle};
 
 #[derive(Debug, Clone)]
 pub struct Day10 {
-    input: Map,
+    input: Field,
+    context: Context,
 }
 
 type Map = Grid<Pipe>;
 
+type Position = (usize, usize);
+
+// The grid with the tile under `S` replaced by the pipe it stands for, and the
+// loop through it.
+#[derive(Debug, Clone)]
+struct Field {
+    grid: Map,
+    start: Position,
+    path: Vec<Position>,
+}
+
 impl Day10 {
     pub fn new() -> Day10 {
-        Day10 { input: Grid::new() }
+        Day10 {
+            input: Field::new(),
+            context: Context::default(),
+        }
     }
 
     pub fn _clear(&mut self) {
-        self.input = Grid::new()
+        self.input = Field::new()
+    }
+
+    // Whether tiles may squeeze between pipes, given at runtime as
+    // `--param squeeze=yes` (the puzzle's rule) or `--param squeeze=no`.
+    fn squeeze(&self) -> Squeeze {
+        match self.context.param("squeeze").unwrap_or("yes") {
+            "yes" => Squeeze::Allowed,
+            "no" => Squeeze::Forbidden,
+            s => panic!("Unknown squeeze rule {}!", s),
+        }
     }
 }
 
+// When squeezing is allowed, the outside leaks through the gaps between
+// neighbouring pipes and a tile is enclosed only if it lies inside the loop.
+// When it is forbidden, a tile is enclosed as soon as the loop cuts it off
+// from the edge of the grid, moving from tile to tile.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Squeeze {
+    Allowed,
+    Forbidden,
+}
+
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 enum Pipe {
     NorthSouth,
@@ -31,64 +66,244 @@ enum Pipe {
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
-enum CommingFrom {
-    North(usize, usize),
-    South(usize, usize),
-    East(usize, usize),
-    West(usize, usize),
+enum Direction {
+    North,
+    East,
+    South,
+    West,
 }
 
-use CommingFrom::{East, North, South, West};
-
-impl CommingFrom {
-    fn next(self, pipe: Pipe) -> Self {
-        match pipe {
-            Pipe::NorthSouth => match self {
-                North(i, j) => North(i + 1, j),
-                South(i, j) => South(i - 1, j),
-                _ => panic!("Deadend!"),
-            },
-            Pipe::EastWest => match self {
-                East(i, j) => East(i, j - 1),
-                West(i, j) => West(i, j + 1),
-                _ => panic!("Deadend!"),
-            },
-            Pipe::NorthEast => match self {
-                North(i, j) => West(i, j + 1),
-                East(i, j) => South(i - 1, j),
-                _ => panic!("Deadend!"),
-            },
-            Pipe::NorthWest => match self {
-                North(i, j) => East(i, j - 1),
-                West(i, j) => South(i - 1, j),
-                _ => panic!("Deadend!"),
-            },
-            Pipe::SouthEast => match self {
-                South(i, j) => West(i, j + 1),
-                East(i, j) => North(i + 1, j),
-                _ => panic!("Deadend! {:?}", self),
-            },
-            Pipe::SouthWest => match self {
-                South(i, j) => East(i, j - 1),
-                West(i, j) => North(i + 1, j),
-                _ => panic!("Deadend!"),
-            },
-            Pipe::Empty => panic!("Deadend!"),
-            Pipe::Start => panic!("Back to start!"),
+use Direction::{East, North, South, West};
+
+const DIRECTIONS: [Direction; 4] = [North, East, South, West];
+
+const PIPES: [Pipe; 6] = [
+    Pipe::NorthSouth,
+    Pipe::EastWest,
+    Pipe::SouthWest,
+    Pipe::SouthEast,
+    Pipe::NorthWest,
+    Pipe::NorthEast,
+];
+
+impl Direction {
+    fn opposite(self) -> Self {
+        match self {
+            North => South,
+            East => West,
+            South => North,
+            West => East,
+        }
+    }
+
+    // The neighbouring position in this direction, if it is on the grid.
+    fn step(self, (i, j): Position, (m, n): (usize, usize)) -> Option<Position> {
+        match self {
+            North => i.checked_sub(1).map(|i| (i, j)),
+            South => (i + 1 < m).then_some((i + 1, j)),
+            West => j.checked_sub(1).map(|j| (i, j)),
+            East => (j + 1 < n).then_some((i, j + 1)),
         }
     }
+}
 
-    fn get_position(self) -> (usize, usize) {
+impl Pipe {
+    // The two directions the pipe leads to.
+    fn exits(self) -> Option<[Direction; 2]> {
         match self {
-            North(i, j) => (i, j),
-            South(i, j) => (i, j),
-            East(i, j) => (i, j),
-            West(i, j) => (i, j),
+            Pipe::NorthSouth => Some([North, South]),
+            Pipe::EastWest => Some([East, West]),
+            Pipe::SouthWest => Some([South, West]),
+            Pipe::SouthEast => Some([South, East]),
+            Pipe::NorthWest => Some([North, West]),
+            Pipe::NorthEast => Some([North, East]),
+            Pipe::Empty | Pipe::Start => None,
         }
     }
+
+    fn connects(self, direction: Direction) -> bool {
+        self.exits().is_some_and(|exits| exits.contains(&direction))
+    }
+
+    fn from_exits(a: Direction, b: Direction) -> Pipe {
+        *PIPES
+            .iter()
+            .find(|pipe| pipe.connects(a) && pipe.connects(b))
+            .unwrap()
+    }
+}
+
+impl Field {
+    fn new() -> Self {
+        Field {
+            grid: Grid::new(),
+            start: (0, 0),
+            path: Vec::new(),
+        }
+    }
+
+    // Finds `S` and works out its pipe: it must join two neighbours that lead
+    // back into it and exactly one such pair must close the loop.
+    fn from_grid(mut grid: Map) -> Result<Self, String> {
+        let (m, n) = grid.dims;
+        let starts: Vec<Position> = (0..m)
+            .flat_map(|i| (0..n).map(move |j| (i, j)))
+            .filter(|&(i, j)| grid[i][j] == Pipe::Start)
+            .collect();
+        let start = match starts[..] {
+            [start] => start,
+            [] => return Err("There is no S on the grid!".to_string()),
+            _ => return Err(format!("There are {} S on the grid!", starts.len())),
+        };
+        let open: Vec<Direction> = DIRECTIONS
+            .into_iter()
+            .filter(|d| {
+                d.step(start, grid.dims)
+                    .is_some_and(|(i, j)| grid[i][j].connects(d.opposite()))
+            })
+            .collect();
+        let mut candidates = Vec::new();
+        for (k, &a) in open.iter().enumerate() {
+            for &b in &open[k + 1..] {
+                grid[start.0][start.1] = Pipe::from_exits(a, b);
+                let mut field = Field {
+                    grid: grid.clone(),
+                    start,
+                    path: Vec::new(),
+                };
+                if let Ok(path) = field.trace() {
+                    field.path = path;
+                    candidates.push(field);
+                }
+            }
+        }
+        match candidates.len() {
+            1 => Ok(candidates.pop().unwrap()),
+            0 => Err(format!(
+                "S at {:?} does not sit on a loop ({} connecting neighbours)!",
+                start,
+                open.len()
+            )),
+            k => Err(format!("S at {:?} could be any of {} pipes!", start, k)),
+        }
+    }
+
+    // The positions along the loop, starting from `S`.
+    fn trace(&self) -> Result<Vec<Position>, String> {
+        let grid = &self.grid;
+        let mut pos = self.start;
+        let mut heading = grid[pos.0][pos.1].exits().unwrap()[0];
+        let mut path = vec![pos];
+        loop {
+            let next = heading
+                .step(pos, grid.dims)
+                .ok_or(format!("Deadend at {:?}: the pipe leaves the grid!", pos))?;
+            let pipe = grid[next.0][next.1];
+            if !pipe.connects(heading.opposite()) {
+                return Err(format!("Deadend at {:?}!", next));
+            }
+            if next == self.start {
+                return Ok(path);
+            }
+            let [a, b] = pipe.exits().unwrap();
+            heading = if a == heading.opposite() { b } else { a };
+            pos = next;
+            path.push(pos);
+        }
+    }
+
+    fn on_loop(&self, path: &[Position]) -> Grid<bool> {
+        let (m, n) = self.grid.dims;
+        let mut on_loop = Grid::from_rows(vec![vec![false; n]; m]);
+        for &(i, j) in path {
+            on_loop[i][j] = true;
+        }
+        on_loop
+    }
+
+    // The tiles enclosed by the loop, row by row.
+    fn enclosed(&self, path: &[Position], squeeze: Squeeze) -> Vec<Position> {
+        let (m, n) = self.grid.dims;
+        let on_loop = self.on_loop(path);
+        let mut enclosed = Vec::new();
+        match squeeze {
+            // Scanning a row, we are inside after crossing the loop an odd
+            // number of times; only the pipes leading north count as a
+            // crossing, so that runs like `L--7` count once and `L--J` twice.
+            Squeeze::Allowed => {
+                for i in 0..m {
+                    let mut inside = false;
+                    for j in 0..n {
+                        if on_loop[i][j] {
+                            inside ^= self.grid[i][j].connects(North);
+                        } else if inside {
+                            enclosed.push((i, j));
+                        }
+                    }
+                }
+            }
+            // Everything the edge of the grid can reach without stepping on
+            // the loop is outside.
+            Squeeze::Forbidden => {
+                let mut outside = Grid::from_rows(vec![vec![false; n]; m]);
+                let mut stack: Vec<Position> = (0..m)
+                    .flat_map(|i| (0..n).map(move |j| (i, j)))
+                    .filter(|&(i, j)| i == 0 || j == 0 || i == m - 1 || j == n - 1)
+                    .collect();
+                while let Some((i, j)) = stack.pop() {
+                    if on_loop[i][j] || outside[i][j] {
+                        continue;
+                    }
+                    outside[i][j] = true;
+                    stack.extend(DIRECTIONS.iter().filter_map(|d| d.step((i, j), (m, n))));
+                }
+                for i in 0..m {
+                    for j in 0..n {
+                        if !on_loop[i][j] && !outside[i][j] {
+                            enclosed.push((i, j));
+                        }
+                    }
+                }
+            }
+        }
+        enclosed
+    }
+
+    // The grid with the loop drawn in box-drawing characters, the enclosed
+    // tiles filled in and everything else blanked out.
+    fn render(&self, path: &[Position], enclosed: &[Position]) -> Vec<String> {
+        let (m, n) = self.grid.dims;
+        let on_loop = self.on_loop(path);
+        let mut rows: Vec<Vec<char>> = vec![vec!['·'; n]; m];
+        for i in 0..m {
+            for j in 0..n {
+                if on_loop[i][j] {
+                    rows[i][j] = match self.grid[i][j] {
+                        Pipe::NorthSouth => '│',
+                        Pipe::EastWest => '─',
+                        Pipe::SouthWest => '┐',
+                        Pipe::SouthEast => '┌',
+                        Pipe::NorthWest => '┘',
+                        Pipe::NorthEast => '└',
+                        Pipe::Empty | Pipe::Start => unreachable!(),
+                    };
+                }
+            }
+        }
+        for &(i, j) in enclosed {
+            rows[i][j] = '█';
+        }
+        rows.into_iter()
+            .map(|row| row.into_iter().collect())
+            .collect()
+    }
 }
 
 impl Puzzle for Day10 {
+    fn configure(&mut self, context: &Context) {
+        self.context = context.clone();
+    }
+
     fn load_input(&mut self) {
         const INPUT: &str = include_str!("../inputs/10.input");
         let rows = INPUT
@@ -109,62 +324,28 @@ impl Puzzle for Day10 {
                     .collect()
             })
             .collect();
-        self.input = Grid::from_rows(rows);
+        self.input = match Field::from_grid(Grid::from_rows(rows)) {
+            Ok(field) => field,
+            Err(e) => panic!("{}", e),
+        };
     }
 
     fn part1(&self) -> String {
-        let start = find_start(&self.input);
-        let grid = &self.input;
-        let mut pipe = grid[start.0][start.1 + 1];
-        let mut pos = West(start.0, start.1 + 1);
-        let mut path = vec![pos];
-        while pipe != Pipe::Start {
-            pos = pos.next(pipe);
-            let (i, j) = pos.get_position();
-            pipe = grid[i][j];
-            path.push(pos);
-        }
-        format!("{:?}", path.len() / 2)
+        format!("{:?}", self.input.path.len() / 2)
     }
 
-    // For this I am using Pick's formula:
-    // Interior points = Area - (Points on the border) / 2 + 1.
-    // To compute the area I am using the trapezoid formula.
-    // The triangle formula or the shoelace formula could be used instead.
     fn part2(&self) -> String {
-        let start = find_start(&self.input);
-        let grid = &self.input;
-        let mut pipe = grid[start.0][start.1 + 1];
-        let mut pos = West(start.0, start.1 + 1);
-        let mut path = vec![pos];
-        while pipe != Pipe::Start {
-            pos = pos.next(pipe);
-            let (i, j) = pos.get_position();
-            pipe = grid[i][j];
-            path.push(pos);
-        }
-        let mut area = 0isize;
-        for i in 0..path.len() - 1 {
-            let (y, x) = path[i].get_position();
-            let (yy, xx) = path[i + 1].get_position();
-            area += (y as isize + yy as isize) * (x as isize - xx as isize);
-        }
-        let (y, x) = path[path.len() - 1].get_position();
-        let (yy, xx) = path[0].get_position();
-        area += (y as isize + yy as isize) * (x as isize - xx as isize);
-        area /= 2;
-        format!("{:?}", area - (path.len() / 2) as isize + 1isize)
+        let path = &self.input.path;
+        let enclosed = self.input.enclosed(path, self.squeeze());
+        format!("{:?}", enclosed.len())
     }
-}
 
-fn find_start(grid: &Map) -> (usize, usize) {
-    let (m, n) = grid.dims;
-    for i in 0..m {
-        for j in 0..n {
-            if grid[i][j] == Pipe::Start {
-                return (i, j);
-            }
+    fn details(&self) -> Vec<String> {
+        if !self.context.details {
+            return Vec::new();
         }
+        let path = &self.input.path;
+        let enclosed = self.input.enclosed(path, self.squeeze());
+        self.input.render(path, &enclosed)
     }
-    (0, 0)
 }
@@ -1,72 +1,209 @@
-use aoc_2023_rust::Puzzle;
-use std::collections::HashSet;
+use aoc_2023_rust::{Context, Puzzle};
 
 #[derive(Debug, Clone)]
 pub struct Day11 {
     input: Input,
+    context: Context,
 }
 
-type Image = HashSet<(isize, isize)>;
+type Position = (isize, isize);
 
+// Galaxies are numbered in reading order, starting from 0.
 #[derive(Debug, Clone)]
 struct Input {
-    image: Image,
+    galaxies: Vec<Position>,
     dims: (usize, usize),
 }
 
+// How many rows or columns each empty row and each empty column becomes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Factors {
+    rows: isize,
+    cols: isize,
+}
+
+impl Factors {
+    // Either a single factor for both, `N`, or `ROWS,COLS`.
+    fn parse(s: &str) -> Factors {
+        let factor = |s: &str| s.trim().parse::<isize>().unwrap();
+        match s.split_once(',') {
+            Some((rows, cols)) => Factors {
+                rows: factor(rows),
+                cols: factor(cols),
+            },
+            None => Factors {
+                rows: factor(s),
+                cols: factor(s),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Metric {
+    Manhattan,
+    Chebyshev,
+    EuclideanSquared,
+}
+
+impl Metric {
+    fn parse(s: &str) -> Metric {
+        match s {
+            "manhattan" => Metric::Manhattan,
+            "chebyshev" => Metric::Chebyshev,
+            "euclidean2" => Metric::EuclideanSquared,
+            _ => panic!("Unknown metric {}!", s),
+        }
+    }
+
+    fn distance(self, (a, b): Position, (c, d): Position) -> isize {
+        let (di, dj) = ((c - a).abs(), (d - b).abs());
+        match self {
+            Metric::Manhattan => di + dj,
+            Metric::Chebyshev => di.max(dj),
+            Metric::EuclideanSquared => di * di + dj * dj,
+        }
+    }
+}
+
+// For every coordinate, how many of the lines before it hold no galaxy.
+fn empty_before(coordinates: impl Iterator<Item = isize>, len: usize) -> Vec<isize> {
+    let mut occupied = vec![false; len];
+    for c in coordinates {
+        occupied[c as usize] = true;
+    }
+    let mut empty = vec![0; len + 1];
+    for (c, &occupied) in occupied.iter().enumerate() {
+        empty[c + 1] = empty[c] + !occupied as isize;
+    }
+    empty
+}
+
+// The sum of |a - b| over all pairs of values.
+fn pairwise_sum(mut values: Vec<isize>) -> isize {
+    values.sort_unstable();
+    let mut below = 0;
+    let mut total = 0;
+    for (k, &v) in values.iter().enumerate() {
+        total += v * k as isize - below;
+        below += v;
+    }
+    total
+}
+
 impl Input {
     fn new() -> Self {
         Input {
-            image: HashSet::new(),
+            galaxies: Vec::new(),
             dims: (0, 0),
         }
     }
 
-    fn expand(&self, scale: isize) -> Input {
-        let mut occupied_rows = HashSet::new();
-        let mut occupied_cols = HashSet::new();
-        for (i, j) in &self.image {
-            occupied_rows.insert(*i);
-            occupied_cols.insert(*j);
-        }
-        let all_rows: HashSet<isize> = (0isize..self.dims.0 as isize).collect();
-        let empty_rows: HashSet<isize> = all_rows.difference(&occupied_rows).copied().collect();
-        let all_cols: HashSet<isize> = (0isize..self.dims.1 as isize).collect();
-        let empty_cols: HashSet<isize> = all_cols.difference(&occupied_cols).copied().collect();
-
-        let mut image_new_rows = HashSet::new();
-        for (a, b) in &self.image {
-            let shift_down = empty_rows.iter().filter(|&i| i < a).count() as isize;
-            image_new_rows.insert((a + (scale - 1) * shift_down, b));
-        }
-        let mut image_new_cols = HashSet::new();
-        for (a, b) in image_new_rows {
-            let shift_right = empty_cols.iter().filter(|&j| j < b).count() as isize;
-            image_new_cols.insert((a, b + (scale - 1) * shift_right));
-        }
+    fn expand(&self, factors: Factors) -> Input {
+        let (m, n) = self.dims;
+        let rows = empty_before(self.galaxies.iter().map(|g| g.0), m);
+        let cols = empty_before(self.galaxies.iter().map(|g| g.1), n);
+        let galaxies = self
+            .galaxies
+            .iter()
+            .map(|&(i, j)| {
+                (
+                    i + (factors.rows - 1) * rows[i as usize],
+                    j + (factors.cols - 1) * cols[j as usize],
+                )
+            })
+            .collect();
         Input {
-            image: image_new_cols,
+            galaxies,
             dims: (
-                self.dims.0 + empty_rows.len(),
-                self.dims.1 + empty_cols.len(),
+                (m as isize + (factors.rows - 1) * rows[m]) as usize,
+                (n as isize + (factors.cols - 1) * cols[n]) as usize,
             ),
         }
     }
+
+    // The sum of the distances between all pairs of galaxies; the rows and
+    // the columns contribute independently.
+    fn total_distance(&self) -> isize {
+        pairwise_sum(self.galaxies.iter().map(|g| g.0).collect())
+            + pairwise_sum(self.galaxies.iter().map(|g| g.1).collect())
+    }
+
+    fn distance(&self, metric: Metric, a: usize, b: usize) -> isize {
+        metric.distance(self.galaxies[a], self.galaxies[b])
+    }
+
+    // For every galaxy, the closest other galaxy and how far it is; ties go
+    // to the lower index.
+    fn nearest(&self, metric: Metric) -> Vec<Option<(usize, isize)>> {
+        (0..self.galaxies.len())
+            .map(|a| {
+                (0..self.galaxies.len())
+                    .filter(|&b| b != a)
+                    .map(|b| (b, self.distance(metric, a, b)))
+                    .min_by_key(|&(b, d)| (d, b))
+            })
+            .collect()
+    }
+
+    // The two galaxies furthest apart, and their distance.
+    fn farthest_pair(&self, metric: Metric) -> Option<(usize, usize, isize)> {
+        let n = self.galaxies.len();
+        (0..n)
+            .flat_map(|a| ((a + 1)..n).map(move |b| (a, b)))
+            .map(|(a, b)| (a, b, self.distance(metric, a, b)))
+            .max_by_key(|&(a, b, d)| (d, std::cmp::Reverse((a, b))))
+    }
 }
 
 impl Day11 {
     pub fn new() -> Day11 {
         Day11 {
             input: Input::new(),
+            context: Context::default(),
         }
     }
 
     pub fn _clear(&mut self) {
         self.input = Input::new()
     }
+
+    // Given at runtime as `--param metric=manhattan` (the default),
+    // `chebyshev` or `euclidean2` for the squared Euclidean distance.
+    fn metric(&self) -> Metric {
+        Metric::parse(self.context.param("metric").unwrap_or("manhattan"))
+    }
+
+    // The galaxies in `--param pair=A,B`, numbered from 1 as in the puzzle,
+    // turned into indices.
+    fn pair(&self, pair: &str) -> Result<(usize, usize), String> {
+        let count = self.input.galaxies.len();
+        let galaxy = |s: &str| match s.trim().parse::<usize>() {
+            Ok(k) if (1..=count).contains(&k) => Ok(k - 1),
+            _ => Err(format!(
+                "pair: there is no galaxy {:?}, they go from 1 to {}",
+                s.trim(),
+                count
+            )),
+        };
+        let (a, b) = pair
+            .split_once(',')
+            .ok_or(format!("pair: {:?} is not two galaxies A,B", pair))?;
+        Ok((galaxy(a)?, galaxy(b)?))
+    }
+
+    // The expansion for a part, given at runtime as e.g. `--param expand2=10`
+    // or `--param expand2=1,1000000` for rows and columns separately.
+    fn factors(&self, name: &str, default: &str) -> Factors {
+        Factors::parse(self.context.param(name).unwrap_or(default))
+    }
 }
 
 impl Puzzle for Day11 {
+    fn configure(&mut self, context: &Context) {
+        self.context = context.clone();
+    }
+
     fn load_input(&mut self) {
         const INPUT: &str = include_str!("../inputs/11.input");
         let mut row_count = 0;
@@ -77,7 +214,7 @@ impl Puzzle for Day11 {
             for (j, c) in line.chars().enumerate() {
                 current_col_count += 1;
                 if c == '#' {
-                    self.input.image.insert((i as isize, j as isize));
+                    self.input.galaxies.push((i as isize, j as isize));
                 }
             }
             col_count = col_count.max(current_col_count);
@@ -86,28 +223,49 @@ impl Puzzle for Day11 {
     }
 
     fn part1(&self) -> String {
-        let expanded_image = self.input.expand(2).image;
-        let mut suma = 0;
-        for galaxy1 in &expanded_image {
-            for galaxy2 in &expanded_image {
-                suma += hamming_dist(*galaxy1, *galaxy2);
-            }
-        }
-        format!("{:?}", suma / 2)
+        let expanded_image = self.input.expand(self.factors("expand1", "2"));
+        format!("{:?}", expanded_image.total_distance())
     }
 
     fn part2(&self) -> String {
-        let expanded_image = self.input.expand(1000000).image;
-        let mut suma = 0;
-        for galaxy1 in &expanded_image {
-            for galaxy2 in &expanded_image {
-                suma += hamming_dist(*galaxy1, *galaxy2);
+        let expanded_image = self.input.expand(self.factors("expand2", "1000000"));
+        format!("{:?}", expanded_image.total_distance())
+    }
+
+    // Galaxies are numbered from 1 here, as in the puzzle. `--param pair=5,9`
+    // asks for the distance between two of them after each expansion, and
+    // `--details` lists nearest neighbours and the farthest pair after the
+    // part 1 expansion.
+    fn details(&self) -> Vec<String> {
+        let metric = self.metric();
+        let mut rows = Vec::new();
+        match self.context.param("pair").map(|pair| self.pair(pair)) {
+            Some(Ok((a, b))) => {
+                for (part, name, default) in [(1, "expand1", "2"), (2, "expand2", "1000000")] {
+                    let expanded_image = self.input.expand(self.factors(name, default));
+                    rows.push(format!(
+                        "part {}: galaxies {} and {} are {} apart",
+                        part,
+                        a + 1,
+                        b + 1,
+                        expanded_image.distance(metric, a, b)
+                    ));
+                }
             }
+            Some(Err(e)) => rows.push(e),
+            None => {}
         }
-        format!("{:?}", suma / 2)
+        if self.context.details {
+            let expanded_image = self.input.expand(self.factors("expand1", "2"));
+            for (a, nearest) in expanded_image.nearest(metric).iter().enumerate() {
+                if let Some((b, d)) = nearest {
+                    rows.push(format!("galaxy {}: nearest is {} at {}", a + 1, b + 1, d));
+                }
+            }
+            if let Some((a, b, d)) = expanded_image.farthest_pair(metric) {
+                rows.push(format!("farthest pair: {} and {} at {}", a + 1, b + 1, d));
+            }
+        }
+        rows
     }
 }
-
-fn hamming_dist((a, b): (isize, isize), (c, d): (isize, isize)) -> isize {
-    (c - a).abs() + (d - b).abs()
-}
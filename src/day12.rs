@@ -1,16 +1,25 @@
-use std::collections::HashMap;
+use std::ops::AddAssign;
 
-use aoc_2023_rust::Puzzle;
+use aoc_2023_rust::{Context, Puzzle};
+use num::{BigUint, One, Zero};
+use rand::Rng;
 use rayon::prelude::*;
 
 #[derive(Debug, Clone)]
 pub struct Day12 {
     input: Input,
+    context: Context,
 }
 
 #[derive(Debug, Clone)]
 struct Input {
-    data: Vec<(Vec<char>, Vec<usize>)>,
+    data: Vec<Record>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Record {
+    springs: Vec<char>,
+    counts: Vec<usize>,
 }
 
 impl Input {
@@ -23,179 +32,271 @@ impl Day12 {
     pub fn new() -> Day12 {
         Day12 {
             input: Input::new(),
+            context: Context::default(),
         }
     }
 
     pub fn _clear(&mut self) {
         self.input = Input::new();
     }
+
+    // How many copies part 2 makes of each record, given at runtime as e.g.
+    // `--param unfold=10`.
+    fn unfold(&self) -> usize {
+        self.context
+            .param("unfold")
+            .map_or(5, |n| n.parse::<usize>().unwrap())
+    }
 }
 
-impl Puzzle for Day12 {
-    fn load_input(&mut self) {
-        const INPUT: &str = include_str!("../inputs/12.input");
-        for line in INPUT.lines() {
-            let (spring_str, spring_nums) = line.split_once(' ').unwrap();
-            self.input.data.push((
-                spring_str.chars().collect::<Vec<_>>(),
-                spring_nums
-                    .split(',')
-                    .map(|s| s.parse::<usize>().unwrap())
-                    .collect(),
-            ));
+// Counting works on tables where `ways[g][i]` is the number of ways to place
+// the groups `counts[g..]` in `springs[i..]`.
+impl Record {
+    // The record repeated `factor` times, the copies of the springs joined by
+    // unknown springs.
+    fn unfold(&self, factor: usize) -> Record {
+        let mut springs = Vec::new();
+        for k in 0..factor {
+            if k > 0 {
+                springs.push('?');
+            }
+            springs.extend(&self.springs);
+        }
+        Record {
+            springs,
+            counts: self.counts.repeat(factor),
         }
     }
 
-    fn part1(&self) -> String {
-        let mut data = self.input.data.clone();
-        for (springs, _) in &mut data {
-            springs.push('.');
+    // Whether a group of `len` damaged springs can start at `i`: none of them
+    // is operational and the spring right after, if any, is not damaged.
+    fn fits(&self, i: usize, len: usize) -> bool {
+        let n = self.springs.len();
+        i + len <= n
+            && !self.springs[i..i + len].contains(&'.')
+            && (i + len == n || self.springs[i + len] != '#')
+    }
+
+    // Where the rest of the record continues after a group at `i`, skipping
+    // the operational spring that ends the group.
+    fn after(&self, i: usize, len: usize) -> usize {
+        (i + len + 1).min(self.springs.len())
+    }
+
+    // `ways[counts.len()]`: no groups left, so no damaged springs either.
+    fn last_row<T: Zero + One + Clone>(&self) -> Vec<T> {
+        let n = self.springs.len();
+        let mut row = vec![T::zero(); n + 1];
+        row[n] = T::one();
+        for i in (0..n).rev() {
+            if self.springs[i] != '#' {
+                row[i] = row[i + 1].clone();
+            }
         }
-        let result: usize = data
-            .par_iter()
-            .map(|(s, c)| {
-                let mut memo = HashMap::new();
-                calc(s, c, State { amount: 0, goal: 0 }, &mut memo)
-            })
-            .sum();
-        format!("{:?}", result)
+        row
     }
 
-    fn part2(&self) -> String {
-        let mut tmp = self.input.data.clone();
-        for (s, _) in &mut tmp {
-            s.push('?');
+    // `ways[g]` from `ways[g + 1]`: at each position the first spring is
+    // either left operational or starts group `g`.
+    fn row<T>(&self, g: usize, next: &[T]) -> Vec<T>
+    where
+        T: Zero + Clone + for<'a> AddAssign<&'a T>,
+    {
+        let n = self.springs.len();
+        let len = self.counts[g];
+        let mut row = vec![T::zero(); n + 1];
+        for i in (0..n).rev() {
+            let mut ways = T::zero();
+            if self.springs[i] != '#' {
+                ways += &row[i + 1];
+            }
+            if self.fits(i, len) {
+                ways += &next[self.after(i, len)];
+            }
+            row[i] = ways;
         }
-        let mut data = Vec::new();
-        for (springs, counts) in tmp {
-            data.push((
-                springs
-                    .iter()
-                    .cycle()
-                    .take(springs.len() * 5)
-                    .copied()
-                    .collect::<Vec<_>>(),
-                counts
-                    .iter()
-                    .cycle()
-                    .take(counts.len() * 5)
-                    .copied()
-                    .collect::<Vec<_>>(),
-            ));
+        row
+    }
+
+    // Keeps only two rows of the table at a time.
+    fn count<T>(&self) -> T
+    where
+        T: Zero + One + Clone + for<'a> AddAssign<&'a T>,
+    {
+        let mut row = self.last_row::<T>();
+        for g in (0..self.counts.len()).rev() {
+            row = self.row(g, &row);
         }
-        for (s, _) in &mut data {
-            s.pop();
-            s.push('.');
+        row[0].clone()
+    }
+
+    fn table(&self) -> Vec<Vec<u128>> {
+        let mut table = vec![self.last_row()];
+        for g in (0..self.counts.len()).rev() {
+            table.push(self.row(g, table.last().unwrap()));
         }
-        let result: usize = data
-            .par_iter()
-            .map(|(s, c)| {
-                let mut memo = HashMap::new();
-                calc(s, c, State { amount: 0, goal: 0 }, &mut memo)
-            })
-            .sum();
-        format!("{:?}", result)
+        table.reverse();
+        table
     }
-}
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
-struct State {
-    amount: usize,
-    goal: usize,
-}
+    fn arrangements(&self) -> Arrangements<'_> {
+        let table = self.table();
+        let stack = if table[0][0] > 0 {
+            vec![(0, 0, String::new())]
+        } else {
+            Vec::new()
+        };
+        Arrangements {
+            record: self,
+            table,
+            stack,
+        }
+    }
 
-fn dot(
-    remaining: &[char],
-    counts: &[usize],
-    state: State,
-    memo: &mut HashMap<(State, usize), usize>,
-) -> usize {
-    if state.amount > 0 {
-        if state.amount == counts[state.goal] {
-            let new_state = State {
-                amount: 0,
-                goal: state.goal + 1,
+    // A uniformly random arrangement, if there is any.
+    fn sample<R: Rng>(&self, rng: &mut R) -> Option<String> {
+        let table = self.table();
+        if table[0][0] == 0 {
+            return None;
+        }
+        let mut pick = rng.gen_range(0..table[0][0]);
+        let (mut g, mut i) = (0, 0);
+        let mut arrangement = String::new();
+        let n = self.springs.len();
+        while g < self.counts.len() {
+            let skip = if self.springs[i] != '#' {
+                table[g][i + 1]
+            } else {
+                0
             };
-            let key = (new_state, remaining.len());
-            if let Some(&a) = memo.get(&key) {
-                a
+            if pick < skip {
+                arrangement.push('.');
+                i += 1;
             } else {
-                let a = calc(remaining, counts, new_state, memo);
-                memo.insert(key, a);
-                a
+                pick -= skip;
+                let len = self.counts[g];
+                arrangement.push_str(&"#".repeat(len));
+                if i + len < n {
+                    arrangement.push('.');
+                }
+                i = self.after(i, len);
+                g += 1;
             }
-        } else {
-            0
-        }
-    } else {
-        let new_state = State {
-            amount: 0,
-            goal: state.goal,
-        };
-        let key = (new_state, remaining.len());
-        if let Some(&a) = memo.get(&key) {
-            a
-        } else {
-            let a = calc(remaining, counts, new_state, memo);
-            memo.insert(key, a);
-            a
         }
+        arrangement.push_str(&".".repeat(n - i));
+        Some(arrangement)
     }
 }
 
-fn kang(
-    remaining: &[char],
-    counts: &[usize],
-    state: State,
-    memo: &mut HashMap<(State, usize), usize>,
-) -> usize {
-    if state.goal == counts.len() {
-        return 0;
-    }
-    if state.amount < counts[state.goal] {
-        let new_state = State {
-            amount: state.amount + 1,
-            goal: state.goal,
-        };
-        let key = (new_state, remaining.len());
-        if let Some(&b) = memo.get(&key) {
-            b
-        } else {
-            let b = calc(remaining, counts, new_state, memo);
-            memo.insert(key, b);
-            b
+// The valid arrangements of a record, found by a depth-first search that
+// only follows branches the table says lead somewhere.
+struct Arrangements<'a> {
+    record: &'a Record,
+    table: Vec<Vec<u128>>,
+    stack: Vec<(usize, usize, String)>,
+}
+
+impl Iterator for Arrangements<'_> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        let record = self.record;
+        let n = record.springs.len();
+        while let Some((g, i, mut arrangement)) = self.stack.pop() {
+            if g == record.counts.len() {
+                arrangement.push_str(&".".repeat(n - i));
+                return Some(arrangement);
+            }
+            let len = record.counts[g];
+            if record.fits(i, len) && self.table[g + 1][record.after(i, len)] > 0 {
+                let mut placed = arrangement.clone();
+                placed.push_str(&"#".repeat(len));
+                if i + len < n {
+                    placed.push('.');
+                }
+                self.stack.push((g + 1, record.after(i, len), placed));
+            }
+            if record.springs[i] != '#' && self.table[g][i + 1] > 0 {
+                arrangement.push('.');
+                self.stack.push((g, i + 1, arrangement));
+            }
         }
-    } else {
-        0
+        None
     }
 }
 
-fn calc(
-    springs: &[char],
-    counts: &[usize],
-    state: State,
-    memo: &mut HashMap<(State, usize), usize>,
-) -> usize {
-    let remaining = if springs.len() > 1 {
-        &springs[1..]
-    } else {
-        &[]
-    };
-    let key = (state, remaining.len());
-    if springs.is_empty() {
-        if state.goal == counts.len() {
-            memo.insert(key, 1);
-            return 1;
-        } else {
-            memo.insert(key, 1);
-            return 0;
+impl Puzzle for Day12 {
+    fn configure(&mut self, context: &Context) {
+        self.context = context.clone();
+    }
+
+    fn load_input(&mut self) {
+        const INPUT: &str = include_str!("../inputs/12.input");
+        for line in INPUT.lines() {
+            let (spring_str, spring_nums) = line.split_once(' ').unwrap();
+            let springs = spring_str.chars().collect::<Vec<_>>();
+            if let Some(c) = springs.iter().find(|c| !".#?".contains(**c)) {
+                panic!("What is this spring: {:?}?", c);
+            }
+            self.input.data.push(Record {
+                springs,
+                counts: spring_nums
+                    .split(',')
+                    .map(|s| s.parse::<usize>().unwrap())
+                    .collect(),
+            });
         }
-    };
+    }
+
+    fn part1(&self) -> String {
+        let result: u128 = self
+            .input
+            .data
+            .par_iter()
+            .map(|record| record.count::<u128>())
+            .sum();
+        format!("{:?}", result)
+    }
 
-    match springs[0] {
-        '.' => dot(remaining, counts, state, memo),
-        '#' => kang(remaining, counts, state, memo),
-        '?' => dot(remaining, counts, state, memo) + kang(remaining, counts, state, memo),
-        _ => panic!("This should not happen!"),
+    fn part2(&self) -> String {
+        let factor = self.unfold();
+        let result: BigUint = self
+            .input
+            .data
+            .par_iter()
+            .map(|record| record.unfold(factor).count::<BigUint>())
+            .sum();
+        format!("{}", result)
+    }
+
+    // Up to `--param list=N` arrangements of every record (3 by default) and,
+    // with `--seed`, one picked at random.
+    fn details(&self) -> Vec<String> {
+        if !self.context.details {
+            return Vec::new();
+        }
+        let list = self
+            .context
+            .param("list")
+            .map_or(3, |n| n.parse::<usize>().unwrap());
+        let mut rng = self.context.rng();
+        let mut rows = Vec::new();
+        for record in &self.input.data {
+            let springs: String = record.springs.iter().collect();
+            let count = record.count::<u128>();
+            rows.push(format!("{}: {} arrangements", springs, count));
+            for arrangement in record.arrangements().take(list) {
+                rows.push(format!("    {}", arrangement));
+            }
+            if count > list as u128 {
+                rows.push(format!("    ... and {} more", count - list as u128));
+            }
+            if self.context.seed.is_some() {
+                if let Some(arrangement) = record.sample(&mut rng) {
+                    rows.push(format!("    sampled {}", arrangement));
+                }
+            }
+        }
+        rows
     }
 }
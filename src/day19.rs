@@ -1,12 +1,9 @@
+use aoc_2023_rust::interval::{Interval, IntervalSet};
+use aoc_2023_rust::parse::{blocks, parse_lines, unsigned};
 use aoc_2023_rust::Puzzle;
 use nom::{
-    branch::alt,
-    bytes::complete::tag,
-    character::complete::{alpha1, digit1},
-    combinator::{map, map_res},
-    multi::separated_list0,
-    sequence::tuple,
-    IResult,
+    branch::alt, bytes::complete::tag, character::complete::alpha1, combinator::map,
+    multi::separated_list0, sequence::tuple, IResult,
 };
 use std::collections::{HashMap, HashSet};
 
@@ -52,7 +49,7 @@ struct Workflow<'a> {
 #[derive(Debug, Clone)]
 struct Condition<'a> {
     category: Category,
-    range: Range,
+    range: IntervalSet<usize>,
     next: &'a str,
 }
 
@@ -74,107 +71,69 @@ enum Category {
 
 use Category::{A, M, S, X};
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
-struct Range {
-    a: usize,
-    b: usize,
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+struct State<'a> {
+    name: &'a str,
+    x: IntervalSet<usize>,
+    m: IntervalSet<usize>,
+    a: IntervalSet<usize>,
+    s: IntervalSet<usize>,
 }
 
-impl Range {
-    fn contains(self, value: usize) -> bool {
-        self.a <= value && value <= self.b
-    }
-
-    fn intersect(self, other: &Range) -> Range {
-        let left = self.a.max(other.a);
-        let right = self.b.min(other.b);
-        Range { a: left, b: right }
+impl<'a> State<'a> {
+    fn get(&self, category: Category) -> &IntervalSet<usize> {
+        match category {
+            X => &self.x,
+            M => &self.m,
+            A => &self.a,
+            S => &self.s,
+        }
     }
 
-    fn complement(self) -> Range {
-        if self.a > 1 {
-            Range {
-                a: 1,
-                b: self.a - 1,
-            }
-        } else {
-            Range {
-                a: self.b + 1,
-                b: 4000,
-            }
+    fn get_mut(&mut self, category: Category) -> &mut IntervalSet<usize> {
+        match category {
+            X => &mut self.x,
+            M => &mut self.m,
+            A => &mut self.a,
+            S => &mut self.s,
         }
     }
 
-    fn len(&self) -> u128 {
-        (self.b - self.a + 1) as u128
-    }
-}
-
-#[derive(Debug, Clone, Eq, PartialEq, Hash)]
-struct State<'a> {
-    name: &'a str,
-    x: Range,
-    m: Range,
-    a: Range,
-    s: Range,
-}
-
-impl<'a> State<'a> {
     fn process(&self, workflows: &'a HashMap<&'a str, Workflow<'a>>) -> Vec<State<'a>> {
         let mut new_states: Vec<State> = Vec::new();
         let wf = &workflows[self.name];
-        for (
-            i,
-            Condition {
-                category,
-                range,
-                next,
-            },
-        ) in wf.conditions.iter().enumerate()
+        let mut rest = self.clone();
+        for Condition {
+            category,
+            range,
+            next,
+        } in &wf.conditions
         {
-            let mut new_state = self.clone();
+            let mut new_state = rest.clone();
             new_state.name = next;
-            for j in 0..i {
-                let cond = &wf.conditions[j];
-                match cond.category {
-                    X => new_state.x = new_state.x.intersect(&cond.range.complement()),
-                    M => new_state.m = new_state.m.intersect(&cond.range.complement()),
-                    A => new_state.a = new_state.a.intersect(&cond.range.complement()),
-                    S => new_state.s = new_state.s.intersect(&cond.range.complement()),
-                }
-            }
-            match category {
-                X => new_state.x = new_state.x.intersect(range),
-                M => new_state.m = new_state.m.intersect(range),
-                A => new_state.a = new_state.a.intersect(range),
-                S => new_state.s = new_state.s.intersect(range),
-            }
+            *new_state.get_mut(*category) = rest.get(*category).intersection(range);
+            *rest.get_mut(*category) = rest.get(*category).difference(range);
             if new_state.non_empty() {
                 new_states.push(new_state);
             }
         }
-        let mut new_state = self.clone();
-        new_state.name = wf.default;
-        for cond in &wf.conditions {
-            match cond.category {
-                X => new_state.x = new_state.x.intersect(&cond.range.complement()),
-                M => new_state.m = new_state.m.intersect(&cond.range.complement()),
-                A => new_state.a = new_state.a.intersect(&cond.range.complement()),
-                S => new_state.s = new_state.s.intersect(&cond.range.complement()),
-            }
-        }
-        if new_state.non_empty() {
-            new_states.push(new_state);
+        rest.name = wf.default;
+        if rest.non_empty() {
+            new_states.push(rest);
         }
         new_states
     }
 
     fn non_empty(&self) -> bool {
-        self.x.a <= self.x.b && self.m.a <= self.m.b && self.a.a <= self.a.b && self.s.a <= self.s.b
+        !(self.x.is_empty() || self.m.is_empty() || self.a.is_empty() || self.s.is_empty())
     }
 
+    // Each category has at most 4000 values, so the counts always fit.
     fn volume(&self) -> u128 {
-        self.x.len() * self.m.len() * self.a.len() * self.s.len()
+        [&self.x, &self.m, &self.a, &self.s]
+            .iter()
+            .map(|set| set.len().unwrap())
+            .product()
     }
 }
 
@@ -254,27 +213,22 @@ fn parse_comparison(s: &str) -> IResult<&str, Comparison> {
     })(s)
 }
 
-fn parse_decimal(s: &str) -> IResult<&str, usize> {
-    map_res(digit1, |d: &str| d.parse::<usize>())(s)
-}
-
-fn parse_condition(s: &str) -> IResult<&str, Condition> {
+fn parse_condition(s: &str) -> IResult<&str, Condition<'_>> {
     map(
         tuple((
             parse_category,
             parse_comparison,
-            parse_decimal,
+            unsigned::<usize>,
             tag(":"),
             parse_name,
         )),
         |(category, comparison, other, _, next)| {
+            // Conditions like `x<1` or `x>4000` match nothing.
             let range = match comparison {
-                LT => Range { a: 1, b: other - 1 },
-                GT => Range {
-                    a: other + 1,
-                    b: 4000,
-                },
-            };
+                LT => Interval::half_open(1, other),
+                GT => Interval::half_open(other + 1, 4001),
+            }
+            .map_or(IntervalSet::new(), IntervalSet::from);
             Condition {
                 category,
                 range,
@@ -284,14 +238,21 @@ fn parse_condition(s: &str) -> IResult<&str, Condition> {
     )(s)
 }
 
-fn parse_conditions(s: &str) -> IResult<&str, Vec<Condition>> {
+fn parse_conditions(s: &str) -> IResult<&str, Vec<Condition<'_>>> {
     separated_list0(tag(","), parse_condition)(s)
 }
 
-fn parse_workflow(s: &str) -> IResult<&str, Workflow> {
+fn parse_workflow(s: &str) -> IResult<&str, Workflow<'_>> {
     map(
-        tuple((parse_name, tag("{"), parse_conditions, tag(","), parse_name)),
-        |(name, _, conditions, _, default)| Workflow {
+        tuple((
+            parse_name,
+            tag("{"),
+            parse_conditions,
+            tag(","),
+            parse_name,
+            tag("}"),
+        )),
+        |(name, _, conditions, _, default, _)| Workflow {
             name,
             conditions,
             default,
@@ -303,13 +264,13 @@ fn parse_part(s: &str) -> IResult<&str, Part> {
     map(
         tuple((
             tag("{x="),
-            parse_decimal,
+            unsigned,
             tag(",m="),
-            parse_decimal,
+            unsigned,
             tag(",a="),
-            parse_decimal,
+            unsigned,
             tag(",s="),
-            parse_decimal,
+            unsigned,
             tag("}"),
         )),
         |(_, x, _, m, _, a, _, s, _)| Part { x, m, a, s },
@@ -319,19 +280,18 @@ fn parse_part(s: &str) -> IResult<&str, Part> {
 impl Puzzle for Day19 {
     fn load_input(&mut self) {
         const INPUT: &str = include_str!("../inputs/19.input");
-        let mut parsing_workflows = true;
-        for line in INPUT.lines() {
-            if line.is_empty() {
-                parsing_workflows = false;
-                continue;
-            }
-            if parsing_workflows {
-                let workflow = parse_workflow(line).unwrap().1;
-                self.input.workflows.insert(workflow.name, workflow);
-            } else {
-                self.input.parts.push(parse_part(line).unwrap().1);
-            }
+        let blocks = blocks(INPUT);
+        let (line, workflows) = blocks[0];
+        for workflow in parse_lines(workflows, parse_workflow)
+            .map_err(|e| e.offset_lines(line))
+            .unwrap()
+        {
+            self.input.workflows.insert(workflow.name, workflow);
         }
+        let (line, parts) = blocks[1];
+        self.input.parts = parse_lines(parts, parse_part)
+            .map_err(|e| e.offset_lines(line))
+            .unwrap();
     }
 
     fn part1(&self) -> String {
@@ -349,10 +309,10 @@ impl Puzzle for Day19 {
     fn part2(&self) -> String {
         let init = State {
             name: "in",
-            x: Range { a: 1, b: 4000 },
-            m: Range { a: 1, b: 4000 },
-            a: Range { a: 1, b: 4000 },
-            s: Range { a: 1, b: 4000 },
+            x: IntervalSet::from(Interval::new(1, 4000)),
+            m: IntervalSet::from(Interval::new(1, 4000)),
+            a: IntervalSet::from(Interval::new(1, 4000)),
+            s: IntervalSet::from(Interval::new(1, 4000)),
         };
         let accepted = bfs(init, &self.input.workflows);
         let count: u128 = accepted.iter().map(|s| s.volume()).sum();
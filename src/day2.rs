@@ -1,141 +1,206 @@
-use aoc_2023_rust::Puzzle;
+use aoc_2023_rust::parse::{parse_all, parse_lines, unsigned};
+use aoc_2023_rust::{Context, Puzzle};
 use nom::{
-    branch::alt,
     bytes::complete::tag,
-    character::complete::digit1,
-    combinator::{map, map_res},
+    character::complete::alpha1,
+    combinator::map,
     multi::separated_list1,
-    sequence::tuple,
+    sequence::{separated_pair, tuple},
     IResult,
 };
+use std::collections::BTreeMap;
 
 #[derive(Debug, Clone)]
 pub struct Day2 {
-    input: Vec<Game>,
+    input: Vec<Game<'static>>,
+    context: Context,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-enum Cube {
-    Red(usize),
-    Green(usize),
-    Blue(usize),
+// Number of cubes of each colour, in a single reveal or in a bag.
+#[derive(Debug, Clone, PartialEq, Default)]
+struct Group<'a> {
+    cubes: BTreeMap<&'a str, usize>,
+}
+
+type Bag<'a> = Group<'a>;
+
+#[derive(Debug, Clone, PartialEq)]
+struct Game<'a> {
+    id: usize,
+    reveals: Vec<Group<'a>>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
-struct Group {
-    red: usize,
-    green: usize,
-    blue: usize,
+struct Violation<'a> {
+    reveal: usize,
+    colour: &'a str,
+    count: usize,
+    limit: usize,
 }
 
-type Game = Vec<Group>;
+const DEFAULT_BAG: &str = "12 red, 13 green, 14 blue";
 
 impl Day2 {
     pub fn new() -> Day2 {
-        Day2 { input: Vec::new() }
+        Day2 {
+            input: Vec::new(),
+            context: Context::default(),
+        }
     }
 
     pub fn _clear(&mut self) {
         self.input = Vec::new()
     }
-}
 
-fn parse_decimal(s: &str) -> IResult<&str, usize> {
-    map_res(digit1, |d: &str| d.parse::<usize>())(s)
+    // The bag limits, given at runtime as e.g. `--param "bag=12 red, 3 cyan"`.
+    fn bag(&self) -> Bag<'_> {
+        let bag = self.context.param("bag").unwrap_or(DEFAULT_BAG);
+        parse_all(bag, parse_group).unwrap()
+    }
 }
 
-fn parse_cube(s: &str) -> IResult<&str, Cube> {
-    map(
-        tuple((
-            parse_decimal,
-            tag(" "),
-            alt((tag("red"), tag("green"), tag("blue"))),
-        )),
-        |t| match t {
-            (n, _, "red") => Cube::Red(n),
-            (n, _, "green") => Cube::Green(n),
-            (n, _, "blue") => Cube::Blue(n),
-            (_, _, _) => panic!("Color not expected!"),
-        },
-    )(s)
+impl<'a> Group<'a> {
+    fn count(&self, colour: &str) -> usize {
+        self.cubes.get(colour).copied().unwrap_or(0)
+    }
+
+    fn power(&self, colours: &[&str]) -> usize {
+        colours.iter().map(|colour| self.count(colour)).product()
+    }
 }
 
-fn parse_group(s: &str) -> IResult<&str, Group> {
-    map(separated_list1(tag(", "), parse_cube), |cubes| {
-        let mut group = Group {
-            red: 0,
-            green: 0,
-            blue: 0,
-        };
-        for cube in cubes {
-            match cube {
-                Cube::Red(n) => {
-                    group.red = n;
-                }
-                Cube::Green(n) => {
-                    group.green = n;
-                }
-                Cube::Blue(n) => {
-                    group.blue = n;
+impl<'a> Game<'a> {
+    // The smallest bag that makes every reveal of the game possible.
+    fn minimum_bag(&self) -> Bag<'a> {
+        let mut bag = Bag::default();
+        for group in &self.reveals {
+            for (&colour, &n) in &group.cubes {
+                let count = bag.cubes.entry(colour).or_insert(0);
+                *count = (*count).max(n);
+            }
+        }
+        bag
+    }
+
+    // Colours missing from `limits` are not in the bag at all.
+    fn violations(&self, limits: &Bag) -> Vec<Violation<'a>> {
+        let mut violations = Vec::new();
+        for (reveal, group) in self.reveals.iter().enumerate() {
+            for (&colour, &count) in &group.cubes {
+                let limit = limits.count(colour);
+                if count > limit {
+                    violations.push(Violation {
+                        reveal,
+                        colour,
+                        count,
+                        limit,
+                    });
                 }
             }
         }
+        violations
+    }
+
+    fn feasible(&self, limits: &Bag) -> bool {
+        self.violations(limits).is_empty()
+    }
+}
+
+fn feasible_games<'a>(games: &'a [Game<'a>], limits: &Bag) -> Vec<&'a Game<'a>> {
+    games.iter().filter(|game| game.feasible(limits)).collect()
+}
+
+// Every colour that shows up anywhere in the games.
+fn colours<'a>(games: &[Game<'a>]) -> Vec<&'a str> {
+    let mut colours: Vec<&str> = games
+        .iter()
+        .flat_map(|game| game.reveals.iter())
+        .flat_map(|group| group.cubes.keys().copied())
+        .collect();
+    colours.sort();
+    colours.dedup();
+    colours
+}
+
+fn parse_cube(s: &str) -> IResult<&str, (usize, &str)> {
+    separated_pair(unsigned, tag(" "), alpha1)(s)
+}
+
+fn parse_group(s: &str) -> IResult<&str, Group<'_>> {
+    map(separated_list1(tag(", "), parse_cube), |cubes| {
+        let mut group = Group::default();
+        for (n, colour) in cubes {
+            *group.cubes.entry(colour).or_insert(0) += n;
+        }
         group
     })(s)
 }
 
-fn parse_game(s: &str) -> IResult<&str, Game> {
+fn parse_game(s: &str) -> IResult<&str, Game<'_>> {
     map(
         tuple((
             tag("Game "),
-            digit1,
+            unsigned,
             tag(": "),
             separated_list1(tag("; "), parse_group),
         )),
-        |(_, _, _, list)| list,
+        |(_, id, _, reveals)| Game { id, reveals },
     )(s)
 }
 
-fn valid_game(game: &Game, bounds: (usize, usize, usize)) -> bool {
-    game.iter()
-        .all(|group| group.red <= bounds.0 && group.green <= bounds.1 && group.blue <= bounds.2)
-}
-
-fn game_power(game: &Game) -> usize {
-    let (red, green, blue) = game.iter().fold((0, 0, 0), |(red, green, blue), group| {
-        (
-            red.max(group.red),
-            green.max(group.green),
-            blue.max(group.blue),
-        )
-    });
-    red * green * blue
-}
-
 impl Puzzle for Day2 {
+    fn configure(&mut self, context: &Context) {
+        self.context = context.clone();
+    }
+
     fn load_input(&mut self) {
         const INPUT: &str = include_str!("../inputs/2.input");
-        for line in INPUT.lines() {
-            self.input.push(parse_game(line).unwrap().1);
-        }
+        self.input = parse_lines(INPUT, parse_game).unwrap();
     }
 
     fn part1(&self) -> String {
-        let red_bound = 12;
-        let green_bound = 13;
-        let blue_bound = 14;
-        let result: usize = self
-            .input
+        let result: usize = feasible_games(&self.input, &self.bag())
             .iter()
-            .enumerate()
-            .filter(|(_, game)| valid_game(game, (red_bound, green_bound, blue_bound)))
-            .map(|(i, _)| i + 1)
+            .map(|game| game.id)
             .sum();
         format!("{:?}", result)
     }
 
     fn part2(&self) -> String {
-        let result: usize = self.input.iter().map(|game: &Game| game_power(game)).sum();
+        let colours = colours(&self.input);
+        let result: usize = self
+            .input
+            .iter()
+            .map(|game| game.minimum_bag().power(&colours))
+            .sum();
         format!("{:?}", result)
     }
+
+    fn details(&self) -> Vec<String> {
+        if !self.context.details {
+            return Vec::new();
+        }
+        let limits = self.bag();
+        let mut rows = Vec::new();
+        for game in &self.input {
+            let minimum = game
+                .minimum_bag()
+                .cubes
+                .iter()
+                .map(|(colour, n)| format!("{} {}", n, colour))
+                .collect::<Vec<_>>()
+                .join(", ");
+            rows.push(format!("game {}: minimum bag {}", game.id, minimum));
+            for v in game.violations(&limits) {
+                rows.push(format!(
+                    "    reveal {}: {} {} over the limit of {}",
+                    v.reveal + 1,
+                    v.count,
+                    v.colour,
+                    v.limit
+                ));
+            }
+        }
+        rows
+    }
 }
@@ -1,3 +1,4 @@
+use aoc_2023_rust::graph::Graph;
 use aoc_2023_rust::Puzzle;
 use num::Integer;
 use std::collections::{HashMap, HashSet, VecDeque};
@@ -12,7 +13,7 @@ impl Day20 {
         Day20 {
             input: Data {
                 modules: HashMap::new(),
-                outputs: HashMap::new(),
+                outputs: Graph::directed(),
             },
         }
     }
@@ -20,7 +21,7 @@ impl Day20 {
     pub fn _clear(&mut self) {
         self.input = Data {
             modules: HashMap::new(),
-            outputs: HashMap::new(),
+            outputs: Graph::directed(),
         };
     }
 }
@@ -30,10 +31,9 @@ impl Puzzle for Day20 {
         const INPUT: &str = include_str!("../inputs/20.input");
         let mut tmp_modules: HashMap<&'static str, Module<'static>> = HashMap::new();
         let mut conjunctions: HashSet<&'static str> = HashSet::new();
-        let mut outputs = HashMap::new();
+        let mut outputs = Graph::directed();
         for line in INPUT.lines() {
             let (module, outs) = line.split_once(" -> ").unwrap();
-            let outs = outs.split(", ").collect::<Vec<_>>();
             let name: &'static str;
             let mtype: ModuleType;
             let state = false;
@@ -74,19 +74,21 @@ impl Puzzle for Day20 {
             if mtype == Conjunction {
                 conjunctions.insert(name);
             }
-            outputs.insert(name, outs);
+            outputs.intern(name);
+            for out in outs.split(", ") {
+                outputs.add_edge(name, out, 1);
+            }
             tmp_modules.insert(name, module);
         }
 
         self.input.modules = tmp_modules.clone();
         self.input.outputs = outputs;
 
-        for (source, outs) in &self.input.outputs {
-            for out in outs {
-                if conjunctions.contains(out) {
-                    if let Some(module) = self.input.modules.get_mut(out) {
-                        module.memory.insert(source, Low);
-                    }
+        for (source, out, _) in self.input.outputs.edges() {
+            let out = self.input.outputs.node(out);
+            if conjunctions.contains(out) {
+                if let Some(module) = self.input.modules.get_mut(out) {
+                    module.memory.insert(self.input.outputs.node(source), Low);
                 }
             }
         }
@@ -206,7 +208,7 @@ type Queue<'a> = VecDeque<(&'a str, &'a str, Signal)>;
 #[derive(Debug, Clone)]
 struct Data<'a> {
     modules: HashMap<&'a str, Module<'a>>,
-    outputs: HashMap<&'a str, Vec<&'a str>>,
+    outputs: Outputs<'a>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -218,13 +220,14 @@ struct Module<'a> {
 }
 
 type Modules<'a> = HashMap<&'a str, Module<'a>>;
-type Outputs<'a> = HashMap<&'a str, Vec<&'a str>>;
+type Outputs<'a> = Graph<&'a str>;
 
 impl<'a> Module<'a> {
     fn send(&self, outputs: &'a Outputs, signal: Signal) -> Queue<'a> {
         let mut queue = VecDeque::new();
-        for out in &outputs[self.name] {
-            queue.push_back((self.name, *out, signal));
+        let i = outputs.index_of(&self.name).unwrap();
+        for &(out, _) in outputs.neighbors(i) {
+            queue.push_back((self.name, *outputs.node(out), signal));
         }
         queue
     }
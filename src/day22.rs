@@ -1,4 +1,7 @@
+use aoc_2023_rust::interval::Interval;
+use aoc_2023_rust::parse::{parse_lines, triple, unsigned};
 use aoc_2023_rust::Puzzle;
+use nom::{character::complete::char, sequence::separated_pair};
 use std::collections::{HashMap, HashSet, VecDeque};
 
 #[derive(Debug, Clone)]
@@ -19,36 +22,21 @@ impl Day22 {
 impl Puzzle for Day22 {
     fn load_input(&mut self) {
         const INPUT: &str = include_str!("../inputs/22.input");
-        let mut bricks: Vec<Brick> = Vec::new();
-        for line in INPUT.lines() {
-            let (start, end) = line.split_once('~').unwrap();
-            let start_coord = start
-                .split(',')
-                .map(|s| s.parse::<usize>().unwrap())
-                .collect::<Vec<_>>();
-            let end_coord = end
-                .split(',')
-                .map(|s| s.parse::<usize>().unwrap())
-                .collect::<Vec<_>>();
-            let x_range = Range {
-                left: start_coord[0].min(end_coord[0]),
-                right: start_coord[0].max(end_coord[0]),
-            };
-            let y_range = Range {
-                left: start_coord[1].min(end_coord[1]),
-                right: start_coord[1].max(end_coord[1]),
-            };
-            let z_range = Range {
-                left: start_coord[2].min(end_coord[2]),
-                right: start_coord[2].max(end_coord[2]),
-            };
-            bricks.push(Brick {
-                x: x_range,
-                y: y_range,
-                z: z_range,
-            });
-        }
-        bricks.sort_by(|a, b| a.z.left.cmp(&b.z.left));
+        let mut bricks: Vec<Brick> = parse_lines(
+            INPUT,
+            separated_pair(triple(unsigned), char('~'), triple(unsigned)),
+        )
+        .unwrap()
+        .into_iter()
+        .map(
+            |(start, end): ((usize, usize, usize), (usize, usize, usize))| Brick {
+                x: Interval::new(start.0.min(end.0), start.0.max(end.0)),
+                y: Interval::new(start.1.min(end.1), start.1.max(end.1)),
+                z: Interval::new(start.2.min(end.2), start.2.max(end.2)),
+            },
+        )
+        .collect();
+        bricks.sort_by_key(|brick| brick.z.start);
         for brick in &bricks {
             self.input.push(brick.fall(&self.input));
         }
@@ -116,39 +104,25 @@ fn cascade(
     fallen
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
-struct Range {
-    left: usize,
-    right: usize,
-}
-
-impl Range {
-    fn intersects(self, other: &Range) -> bool {
-        let left = self.left.max(other.left);
-        let right = self.right.min(other.right);
-        left <= right
-    }
-}
-
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 struct Brick {
-    x: Range,
-    y: Range,
-    z: Range,
+    x: Interval<usize>,
+    y: Interval<usize>,
+    z: Interval<usize>,
 }
 
 impl Brick {
     fn obstructed_by(&self, other: &Self) -> bool {
-        self.x.intersects(&other.x) && self.y.intersects(&other.y) && other.z.right < self.z.left
+        self.x.intersects(&other.x) && self.y.intersects(&other.y) && other.z.end < self.z.start
     }
 
     fn find_obstruction(&self, bricks: &Vec<Brick>) -> Brick {
         let mut obstruction = Brick {
-            z: Range { left: 0, right: 0 },
+            z: Interval::new(0, 0),
             ..*self
         };
         for b in bricks {
-            if self.obstructed_by(b) && b.z.right > obstruction.z.right {
+            if self.obstructed_by(b) && b.z.end > obstruction.z.end {
                 obstruction = *b;
             }
         }
@@ -157,10 +131,10 @@ impl Brick {
 
     fn fall(&self, bricks: &Vec<Brick>) -> Brick {
         let obsrtuction = self.find_obstruction(bricks);
-        let z_range = Range {
-            left: obsrtuction.z.right + 1,
-            right: obsrtuction.z.right + 1 + self.z.right - self.z.left,
-        };
+        let z_range = Interval::new(
+            obsrtuction.z.end + 1,
+            obsrtuction.z.end + 1 + self.z.end - self.z.start,
+        );
         Brick {
             z: z_range,
             ..*self
@@ -170,7 +144,7 @@ impl Brick {
     fn supports(&self, bricks: &Vec<Brick>) -> Vec<Brick> {
         let mut supported: Vec<Brick> = Vec::new();
         for brick in bricks {
-            if brick.obstructed_by(self) && brick.z.left == self.z.right + 1 {
+            if brick.obstructed_by(self) && brick.z.start == self.z.end + 1 {
                 supported.push(*brick);
             }
         }
@@ -180,7 +154,7 @@ impl Brick {
     fn supported_by(&self, bricks: &Vec<Brick>) -> Vec<Brick> {
         let mut supported_by: Vec<Brick> = Vec::new();
         for brick in bricks {
-            if self.obstructed_by(brick) && self.z.left == brick.z.right + 1 {
+            if self.obstructed_by(brick) && self.z.start == brick.z.end + 1 {
                 supported_by.push(*brick);
             }
         }
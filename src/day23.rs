@@ -1,3 +1,4 @@
+use aoc_2023_rust::graph::Graph;
 use aoc_2023_rust::Puzzle;
 use std::collections::{HashMap, HashSet, VecDeque};
 
@@ -84,11 +85,6 @@ fn step(input: &Input, position: Position, prev: Position) -> Vec<Position> {
     nei
 }
 
-struct Graph {
-    adj: HashMap<Position, HashSet<Position>>,
-    weight: HashMap<(Position, Position), usize>,
-}
-
 fn straight_path(
     data: &Input,
     position: Position,
@@ -106,54 +102,28 @@ fn straight_path(
     (pos, d, nei)
 }
 
-fn compute_graph(data: &Input, start: Position) -> Graph {
+fn add_path(graph: &mut Graph<Position>, from: Position, to: Position, d: usize) {
+    let i = graph.intern(from);
+    let j = graph.intern(to);
+    if graph.weight(i, j).is_none() {
+        graph.add_edge_by_index(i, j, d);
+    }
+}
+
+fn compute_graph(data: &Input, start: Position) -> Graph<Position> {
     let mut visited: HashSet<Position> = HashSet::new();
     visited.insert(start);
     let mut queue: VecDeque<(Position, Position)> = VecDeque::new();
-    let mut graph: Graph = Graph {
-        adj: HashMap::new(),
-        weight: HashMap::new(),
-    };
+    let mut graph: Graph<Position> = Graph::undirected();
     let (new_position, d, nei) = straight_path(data, start, start);
-    graph.weight.insert((start, new_position), d);
-    graph.weight.insert((new_position, start), d);
-    if let Some(v) = graph.adj.get_mut(&start) {
-        v.insert(new_position);
-    } else {
-        let mut v: HashSet<Position> = HashSet::new();
-        v.insert(new_position);
-        graph.adj.insert(start, v);
-    }
-    if let Some(v) = graph.adj.get_mut(&new_position) {
-        v.insert(start);
-    } else {
-        let mut v: HashSet<Position> = HashSet::new();
-        v.insert(start);
-        graph.adj.insert(new_position, v);
-    }
+    add_path(&mut graph, start, new_position, d);
     for p in nei {
         queue.push_back((new_position, p));
     }
     visited.insert(new_position);
     while let Some((position, next)) = queue.pop_front() {
         let (new_position, d, nei) = straight_path(data, next, position);
-        graph.weight.insert((position, new_position), d + 1);
-        graph.weight.insert((new_position, position), d + 1);
-
-        if let Some(v) = graph.adj.get_mut(&position) {
-            v.insert(new_position);
-        } else {
-            let mut v: HashSet<Position> = HashSet::new();
-            v.insert(new_position);
-            graph.adj.insert(position, v);
-        }
-        if let Some(v) = graph.adj.get_mut(&new_position) {
-            v.insert(position);
-        } else {
-            let mut v: HashSet<Position> = HashSet::new();
-            v.insert(position);
-            graph.adj.insert(new_position, v);
-        }
+        add_path(&mut graph, position, new_position, d + 1);
         if !visited.contains(&new_position) {
             for p in nei {
                 queue.push_back((new_position, p));
@@ -164,7 +134,7 @@ fn compute_graph(data: &Input, start: Position) -> Graph {
     graph
 }
 
-fn bfs(graph: &Graph, start: Position, goal: Position) -> usize {
+fn bfs(graph: &Graph<Position>, start: Position, goal: Position) -> usize {
     let mut queue: VecDeque<State> = VecDeque::new();
     let mut visited: HashSet<_> = HashSet::new();
     visited.insert(start);
@@ -180,15 +150,16 @@ fn bfs(graph: &Graph, start: Position, goal: Position) -> usize {
         if state.pos == goal {
             dist = dist.max(state.dist);
         }
-        if let Some(nei) = graph.adj.get(&state.pos) {
-            for next in nei {
-                if !state.visited.contains(next) {
+        if let Some(i) = graph.index_of(&state.pos) {
+            for &(j, weight) in graph.neighbors(i) {
+                let next = *graph.node(j);
+                if !state.visited.contains(&next) {
                     let mut visited = state.visited.clone();
-                    visited.insert(*next);
+                    visited.insert(next);
                     queue.push_back(State {
-                        pos: *next,
+                        pos: next,
                         visited,
-                        dist: state.dist + graph.weight[&(state.pos, *next)],
+                        dist: state.dist + weight,
                     });
                 }
             }
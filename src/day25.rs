@@ -1,105 +1,132 @@
-use aoc_2023_rust::Puzzle;
-use rand::seq::SliceRandom;
-use std::collections::HashMap;
+use aoc_2023_rust::graph::Graph;
+use aoc_2023_rust::{Context, Puzzle};
+use rand::{seq::SliceRandom, Rng};
 
 #[derive(Debug, Clone)]
 pub struct Day25 {
-    input: Graph<'static>,
+    input: Graph<&'static str>,
+    context: Context,
 }
 
 impl Day25 {
     pub fn new() -> Day25 {
         Day25 {
-            input: Graph::new(),
+            input: Graph::undirected(),
+            context: Context::default(),
         }
     }
 
     pub fn _clear(&mut self) {
-        self.input = Graph::new();
+        self.input = Graph::undirected();
     }
 }
 
 impl Puzzle for Day25 {
+    fn configure(&mut self, context: &Context) {
+        self.context = context.clone();
+    }
+
     fn load_input(&mut self) {
         const INPUT: &str = include_str!("../inputs/25.input");
         for line in INPUT.lines() {
             let (left, right) = line.split_once(": ").unwrap();
-            let mut edges = right.split(' ').map(|n| (left, n)).collect::<Vec<_>>();
-            self.input.sizes.insert(left, 1);
-            for (_, b) in &edges {
-                self.input.sizes.insert(b, 1);
+            for n in right.split(' ') {
+                self.input.add_edge(left, n, 1);
             }
-            self.input.edges.append(&mut edges);
         }
     }
 
     fn part1(&self) -> String {
-        let g = self.input.clone();
-        let (left, right) = g.min_cut();
-        format!("{:?}", left * right)
+        match self.input.min_cut() {
+            Some(cut) => {
+                let (left, right) = cut.partition;
+                format!("{:?}", left.len() * right.len())
+            }
+            None => "the graph has fewer than two nodes".to_string(),
+        }
     }
 
     fn part2(&self) -> String {
         format!("{:?}", "Push the Big Red Button!")
     }
-}
 
-type Edge<'a> = (&'a str, &'a str);
-
-#[derive(Debug, Clone)]
-struct Graph<'a> {
-    edges: Vec<Edge<'a>>,
-    sizes: HashMap<&'a str, usize>,
+    // With a seed, cross-check the cut with Karger's randomised contraction.
+    // Contraction only finds two-way cuts of a connected graph, so a graph
+    // that is already in pieces gets its components listed instead.
+    fn details(&self) -> Vec<String> {
+        let Some(seed) = self.context.seed else {
+            return Vec::new();
+        };
+        let components = self.input.connected_components();
+        if components.len() > 1 {
+            let sizes: Vec<String> = components.iter().map(|c| c.len().to_string()).collect();
+            return vec![format!(
+                "karger: the graph is already in {} components of sizes {}",
+                components.len(),
+                sizes.join(" x ")
+            )];
+        }
+        let Some(target) = self.input.min_cut().map(|cut| cut.weight) else {
+            return vec!["karger: the graph has fewer than two nodes".to_string()];
+        };
+        let mut rng = self.context.rng();
+        let ((left, right), trials) = karger(&self.input, target, &mut rng);
+        vec![format!(
+            "karger: cut of {} into {} x {} after {} trials (seed {})",
+            target, left, right, trials, seed
+        )]
+    }
 }
 
-impl<'a> Graph<'a> {
-    fn new() -> Graph<'a> {
-        Graph {
-            edges: Vec::new(),
-            sizes: HashMap::new(),
+// Repeats random contractions until one leaves at most `target` edges between
+// the two remaining components; the graph must be connected. Returns their
+// sizes and the number of trials.
+fn karger<N, R>(graph: &Graph<N>, target: usize, rng: &mut R) -> ((usize, usize), usize)
+where
+    N: Clone + Eq + std::hash::Hash,
+    R: Rng,
+{
+    let mut edges: Vec<(usize, usize)> = graph.edges().iter().map(|&(i, j, _)| (i, j)).collect();
+    let mut trials = 0;
+    loop {
+        trials += 1;
+        edges.shuffle(rng);
+        let mut parent: Vec<usize> = (0..graph.len()).collect();
+        let mut components = graph.len();
+        for &(i, j) in &edges {
+            if components == 2 {
+                break;
+            }
+            let (a, b) = (find(&mut parent, i), find(&mut parent, j));
+            if a != b {
+                parent[b] = a;
+                components -= 1;
+            }
         }
-    }
-    fn contract(mut self, e: &Edge<'a>) -> Graph<'a> {
-        let edges: Vec<Edge<'a>> = self
-            .edges
+        let cut = edges
             .iter()
-            .filter_map(|(x, y)| {
-                if (*x, *y) == (e.0, e.1) || (*x, *y) == (e.1, e.0) {
-                    None
-                } else if *x == e.1 {
-                    Some((e.0, *y))
-                } else if *y == e.1 {
-                    Some((*x, e.0))
-                } else {
-                    Some((*x, *y))
-                }
-            })
-            .collect();
-        let size_b = self.sizes[e.1];
-        let size_a = self.sizes[e.0];
-        self.sizes.remove(e.1);
-        self.sizes.insert(e.0, size_a + size_b);
-        self.edges = edges;
-        self
+            .filter(|&&(i, j)| find(&mut parent, i) != find(&mut parent, j))
+            .count();
+        if cut <= target {
+            let root = find(&mut parent, 0);
+            let left = (0..graph.len())
+                .filter(|&i| find(&mut parent, i) == root)
+                .count();
+            return ((left, graph.len() - left), trials);
+        }
     }
+}
 
-    fn min_cut(self) -> (usize, usize) {
-        let mut min = usize::MAX;
-        let mut sizes = (0, 0);
-        while min > 3 {
-            let mut rng = rand::thread_rng();
-            let mut g = self.clone();
-            let mut n = g.sizes.len();
-            while n > 2 {
-                let e = *g.edges.choose(&mut rng).unwrap();
-                let gg = g.contract(&e);
-                n = gg.sizes.len();
-                g = gg;
-            }
-            let s = g.sizes.iter().map(|(a, n)| n).copied().collect::<Vec<_>>();
-            sizes = (s[0], s[1]);
-            min = g.edges.len();
-        }
-        sizes
+fn find(parent: &mut [usize], i: usize) -> usize {
+    let mut root = i;
+    while parent[root] != root {
+        root = parent[root];
+    }
+    let mut i = i;
+    while parent[i] != root {
+        let next = parent[i];
+        parent[i] = root;
+        i = next;
     }
+    root
 }
@@ -1,106 +1,202 @@
-use aoc_2023_rust::Puzzle;
-use std::collections::{HashMap, HashSet};
+use aoc_2023_rust::{Context, Grid, Puzzle};
 
 #[derive(Debug, Clone)]
 pub struct Day3 {
-    input: (Numbers, Symbols, Gears),
+    input: Schematic,
+    context: Context,
 }
 
-type Numbers = HashMap<(isize, isize, isize), isize>;
-type Symbols = HashSet<(isize, isize)>;
-type Gears = HashSet<(isize, isize)>;
+// A part number spans the cells `start..=end` of `row`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PartNumber {
+    value: usize,
+    row: usize,
+    start: usize,
+    end: usize,
+}
 
-impl Day3 {
-    pub fn new() -> Day3 {
-        Day3 {
-            input: (HashMap::new(), HashSet::new(), HashSet::new()),
-        }
-    }
+type Position = (usize, usize);
 
-    pub fn _clear(&mut self) {
-        self.input = (HashMap::new(), HashSet::new(), HashSet::new());
-    }
+#[derive(Debug, Clone)]
+struct Schematic {
+    grid: Grid<char>,
+    numbers: Vec<PartNumber>,
+    // For every cell, the index in `numbers` of the number covering it.
+    index: Grid<Option<usize>>,
 }
 
-impl Puzzle for Day3 {
-    fn load_input(&mut self) {
-        const INPUT: &str = include_str!("../inputs/3.input");
-        let mut numbers: Numbers = HashMap::new();
-        let mut symbols: Symbols = HashSet::new();
-        let mut gears: Gears = HashSet::new();
-        for (i, line) in INPUT.lines().enumerate() {
-            let mut n: isize = 0;
-            let mut start = 0;
-            let mut reading_number = false;
-            for (j, c) in line.chars().enumerate() {
-                if c.is_ascii_digit() {
-                    reading_number = true;
-                    n = n * 10 + c.to_digit(10).unwrap() as isize;
-                } else {
-                    if reading_number {
-                        numbers.insert((i as isize, start as isize, j as isize - 1), n);
-                        reading_number = false;
-                        n = 0
-                    }
-                    start = j + 1;
-                    if c != '.' {
-                        symbols.insert((i as isize, j as isize));
-                    }
-                    if c == '*' {
-                        gears.insert((i as isize, j as isize));
-                    }
+// A symbol is a gear when exactly `count` numbers are adjacent to it; any
+// symbol qualifies when `symbol` is `None`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct GearRule {
+    symbol: Option<char>,
+    count: usize,
+}
+
+const DEFAULT_GEAR: &str = "*,2";
+
+impl Schematic {
+    fn new() -> Self {
+        Schematic {
+            grid: Grid::new(),
+            numbers: Vec::new(),
+            index: Grid::new(),
+        }
+    }
+
+    fn from_rows(rows: Vec<Vec<char>>) -> Self {
+        let grid = Grid::from_rows(rows);
+        let (m, n) = grid.dims;
+        let mut numbers = Vec::new();
+        let mut index = Grid::from_rows(vec![vec![None; n]; m]);
+        for i in 0..m {
+            let mut j = 0;
+            while j < n {
+                if !grid[i][j].is_ascii_digit() {
+                    j += 1;
+                    continue;
                 }
+                let start = j;
+                let mut value = 0;
+                while j < n && grid[i][j].is_ascii_digit() {
+                    value = value * 10 + grid[i][j].to_digit(10).unwrap() as usize;
+                    index[i][j] = Some(numbers.len());
+                    j += 1;
+                }
+                numbers.push(PartNumber {
+                    value,
+                    row: i,
+                    start,
+                    end: j - 1,
+                });
             }
-            if reading_number {
-                numbers.insert((i as isize, start as isize, line.len() as isize - 1), n);
-            }
         }
-        self.input = (numbers, symbols, gears);
+        Schematic {
+            grid,
+            numbers,
+            index,
+        }
     }
 
-    fn part1(&self) -> String {
-        let mut suma = 0;
-        for ((line, begin, end), n) in &self.input.0 {
-            if check_for_symbol(*line, *begin, *end, &self.input.1) {
-                suma += n;
+    fn is_symbol(&self, (i, j): Position) -> bool {
+        let c = self.grid[i][j];
+        c != '.' && !c.is_ascii_digit()
+    }
+
+    fn symbols(&self) -> Vec<Position> {
+        let (m, n) = self.grid.dims;
+        (0..m)
+            .flat_map(|i| (0..n).map(move |j| (i, j)))
+            .filter(|&p| self.is_symbol(p))
+            .collect()
+    }
+
+    // Cells around the box from (top, left) to (bottom, right), clipped to the
+    // grid.
+    fn around(&self, top: usize, left: usize, bottom: usize, right: usize) -> Vec<Position> {
+        let (m, n) = self.grid.dims;
+        let mut cells = Vec::new();
+        for i in top.saturating_sub(1)..=(bottom + 1).min(m - 1) {
+            for j in left.saturating_sub(1)..=(right + 1).min(n - 1) {
+                if i < top || i > bottom || j < left || j > right {
+                    cells.push((i, j));
+                }
             }
         }
-        format!("{:?}", suma)
+        cells
     }
 
-    fn part2(&self) -> String {
-        let mut suma = 0;
-        for (a, b) in &self.input.2 {
-            suma += check_for_numbers(*a, *b, &self.input.0)
-        }
-        format!("{:?}", suma)
+    // Indices of the numbers adjacent to the cell at `position`.
+    fn numbers_adjacent_to(&self, (i, j): Position) -> Vec<usize> {
+        let mut adjacent: Vec<usize> = self
+            .around(i, j, i, j)
+            .into_iter()
+            .filter_map(|(a, b)| self.index[a][b])
+            .collect();
+        adjacent.sort();
+        adjacent.dedup();
+        adjacent
+    }
+
+    fn symbols_adjacent_to(&self, number: usize) -> Vec<Position> {
+        let PartNumber {
+            row, start, end, ..
+        } = self.numbers[number];
+        self.around(row, start, row, end)
+            .into_iter()
+            .filter(|&p| self.is_symbol(p))
+            .collect()
+    }
+
+    fn gears(&self, rule: GearRule) -> Vec<(Position, Vec<usize>)> {
+        self.symbols()
+            .into_iter()
+            .filter(|&(i, j)| rule.symbol.is_none_or(|c| self.grid[i][j] == c))
+            .map(|p| (p, self.numbers_adjacent_to(p)))
+            .filter(|(_, numbers)| numbers.len() == rule.count)
+            .collect()
     }
 }
 
-fn check_for_numbers(a: isize, b: isize, numbers: &HashMap<(isize, isize, isize), isize>) -> isize {
-    let mut adjacent = HashSet::new();
-    for ((line, begin, end), n) in numbers {
-        if (*line == a - 1 || *line == a || *line == a + 1) && *begin <= b + 1 && *end >= b - 1 {
-            adjacent.insert((*line, *begin, *end, *n));
+impl Day3 {
+    pub fn new() -> Day3 {
+        Day3 {
+            input: Schematic::new(),
+            context: Context::default(),
         }
     }
-    if adjacent.len() == 2 {
-        adjacent.iter().map(|(_, _, _, n)| *n).product()
-    } else {
-        0
+
+    pub fn _clear(&mut self) {
+        self.input = Schematic::new();
     }
-}
 
-fn check_for_symbol(line: isize, a: isize, b: isize, symbols: &HashSet<(isize, isize)>) -> bool {
-    // Check lines above and below
-    for j in (a - 1)..(b + 2) {
-        if symbols.contains(&(line - 1, j)) || symbols.contains(&(line + 1, j)) {
-            return true;
+    // The gear rule, given at runtime as e.g. `--param gear=#,3` or
+    // `--param gear=any,2`.
+    fn gear_rule(&self) -> GearRule {
+        let rule = self.context.param("gear").unwrap_or(DEFAULT_GEAR);
+        let (symbol, count) = rule.split_once(',').unwrap();
+        GearRule {
+            symbol: if symbol == "any" {
+                None
+            } else {
+                symbol.chars().next()
+            },
+            count: count.parse::<usize>().unwrap(),
         }
     }
-    // Check same line
-    if symbols.contains(&(line, a - 1)) || symbols.contains(&(line, b + 1)) {
-        return true;
+}
+
+impl Puzzle for Day3 {
+    fn configure(&mut self, context: &Context) {
+        self.context = context.clone();
+    }
+
+    fn load_input(&mut self) {
+        const INPUT: &str = include_str!("../inputs/3.input");
+        let rows = INPUT.lines().map(|line| line.chars().collect()).collect();
+        self.input = Schematic::from_rows(rows);
+    }
+
+    fn part1(&self) -> String {
+        let suma: usize = (0..self.input.numbers.len())
+            .filter(|&k| !self.input.symbols_adjacent_to(k).is_empty())
+            .map(|k| self.input.numbers[k].value)
+            .sum();
+        format!("{:?}", suma)
+    }
+
+    fn part2(&self) -> String {
+        let suma: usize = self
+            .input
+            .gears(self.gear_rule())
+            .iter()
+            .map(|(_, numbers)| {
+                numbers
+                    .iter()
+                    .map(|&k| self.input.numbers[k].value)
+                    .product::<usize>()
+            })
+            .sum();
+        format!("{:?}", suma)
     }
-    false
 }
@@ -1,76 +1,86 @@
-use nom::{
-    bytes::complete::tag,
-    character::complete::{digit1, multispace0, multispace1},
-    combinator::{map, map_res},
-    error::ParseError,
-    multi::separated_list1,
-    sequence::{delimited, tuple},
-    IResult,
-};
+use nom::{bytes::complete::tag, combinator::map, sequence::tuple, IResult};
 use std::collections::HashSet;
 
-use aoc_2023_rust::Puzzle;
+use aoc_2023_rust::parse::{parse_lines, spaced, unsigned, ws};
+use aoc_2023_rust::{Context, Puzzle};
 
 #[derive(Debug, Clone)]
 pub struct Day4 {
     input: Vec<Card>,
+    context: Context,
 }
 
 #[derive(Debug, Clone)]
 struct Card {
-    winning_nums: HashSet<usize>,
-    actual_nums: Vec<usize>,
+    id: usize,
+    matches: usize,
 }
 
 impl Card {
-    fn eval(&self) -> u32 {
-        self.actual_nums.iter().fold(0, |count, n| {
-            if self.winning_nums.contains(n) {
-                count + 1
-            } else {
-                count
-            }
-        })
+    fn new(id: usize, winning_nums: HashSet<usize>, actual_nums: Vec<usize>) -> Card {
+        let matches = actual_nums
+            .iter()
+            .filter(|n| winning_nums.contains(n))
+            .count();
+        Card { id, matches }
     }
 }
 
-fn ws<'a, F: 'a, O, E: ParseError<&'a str>>(
-    inner: F,
-) -> impl FnMut(&'a str) -> IResult<&'a str, O, E>
-where
-    F: Fn(&'a str) -> IResult<&'a str, O, E>,
-{
-    delimited(multispace0, inner, multispace0)
+// How a card fared in the part 2 cascade: how many copies of it we end up
+// with, which earlier cards (by index) produced those copies and how many
+// copies it would have handed out to cards past the end of the table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Outcome {
+    copies: usize,
+    sources: Vec<(usize, usize)>,
+    lost: usize,
 }
 
-fn parse_decimal(s: &str) -> IResult<&str, usize> {
-    map_res(digit1, |d: &str| d.parse::<usize>())(s)
+fn cascade(cards: &[Card]) -> Vec<Outcome> {
+    let mut outcomes: Vec<Outcome> = cards
+        .iter()
+        .map(|_| Outcome {
+            copies: 1,
+            sources: Vec::new(),
+            lost: 0,
+        })
+        .collect();
+    for (i, card) in cards.iter().enumerate() {
+        let copies = outcomes[i].copies;
+        let last = (i + card.matches).min(cards.len() - 1);
+        for outcome in &mut outcomes[(i + 1)..=last] {
+            outcome.copies += copies;
+            outcome.sources.push((i, copies));
+        }
+        outcomes[i].lost = (i + card.matches - last) * copies;
+    }
+    outcomes
 }
 
 fn parse_number_list(s: &str) -> IResult<&str, Vec<usize>> {
-    separated_list1(multispace1, parse_decimal)(s)
+    spaced(unsigned)(s)
 }
 
 fn parse_card(s: &str) -> IResult<&str, Card> {
     map(
         tuple((
             ws(tag("Card")),
-            digit1,
+            unsigned,
             ws(tag(":")),
             parse_number_list,
             ws(tag("|")),
             parse_number_list,
         )),
-        |(_, _, _, list1, _, list2)| Card {
-            winning_nums: list1.into_iter().collect::<HashSet<_>>(),
-            actual_nums: list2,
-        },
+        |(_, id, _, list1, _, list2)| Card::new(id, list1.into_iter().collect(), list2),
     )(s)
 }
 
 impl Day4 {
     pub fn new() -> Day4 {
-        Day4 { input: Vec::new() }
+        Day4 {
+            input: Vec::new(),
+            context: Context::default(),
+        }
     }
 
     pub fn _clear(&mut self) {
@@ -79,11 +89,13 @@ impl Day4 {
 }
 
 impl Puzzle for Day4 {
+    fn configure(&mut self, context: &Context) {
+        self.context = context.clone();
+    }
+
     fn load_input(&mut self) {
         const INPUT: &str = include_str!("../inputs/4.input");
-        for line in INPUT.lines() {
-            self.input.push(parse_card(line).unwrap().1);
-        }
+        self.input = parse_lines(INPUT, parse_card).unwrap();
     }
 
     fn part1(&self) -> String {
@@ -91,11 +103,10 @@ impl Puzzle for Day4 {
             .input
             .iter()
             .map(|card| {
-                let e = card.eval();
-                if e == 0 {
+                if card.matches == 0 {
                     0
                 } else {
-                    usize::pow(2, e - 1)
+                    usize::pow(2, card.matches as u32 - 1)
                 }
             })
             .sum();
@@ -103,17 +114,39 @@ impl Puzzle for Day4 {
     }
 
     fn part2(&self) -> String {
-        let mut multiplicities: Vec<usize> = Vec::with_capacity(self.input.len());
-        self.input.iter().for_each(|_| {
-            multiplicities.push(1);
-        });
-        self.input.iter().enumerate().for_each(|(i, card)| {
-            let k = card.eval() as usize;
-            for j in (i + 1)..(i + 1 + k) {
-                multiplicities[j] += multiplicities[i];
-            }
-        });
-        let total = multiplicities.iter().sum::<usize>();
+        let total = cascade(&self.input)
+            .iter()
+            .map(|outcome| outcome.copies)
+            .sum::<usize>();
         format!("{:?}", total)
     }
+
+    fn details(&self) -> Vec<String> {
+        if !self.context.details {
+            return Vec::new();
+        }
+        let mut rows = Vec::new();
+        for (card, outcome) in self.input.iter().zip(cascade(&self.input)) {
+            let sources = outcome
+                .sources
+                .iter()
+                .map(|(i, n)| format!("{} from card {}", n, self.input[*i].id))
+                .collect::<Vec<_>>();
+            let mut row = format!(
+                "card {}: {} matches, {} copies",
+                card.id, card.matches, outcome.copies
+            );
+            if !sources.is_empty() {
+                row.push_str(&format!(" (extra: {})", sources.join(", ")));
+            }
+            if outcome.lost > 0 {
+                row.push_str(&format!(
+                    ", {} copies past the last card were dropped",
+                    outcome.lost
+                ));
+            }
+            rows.push(row);
+        }
+        rows
+    }
 }
@@ -1,115 +1,193 @@
-use aoc_2023_rust::Puzzle;
-use std::collections::HashSet;
+use aoc_2023_rust::graph::Graph;
+use aoc_2023_rust::interval::{Interval, IntervalSet, PiecewiseMap};
+use aoc_2023_rust::parse::{blocks, key_value, parse_all, spaced, unsigned};
+use aoc_2023_rust::{Context, Puzzle};
+use nom::{
+    bytes::complete::tag,
+    character::complete::{alpha1, line_ending, space1},
+    combinator::map,
+    multi::separated_list1,
+    sequence::{separated_pair, terminated, tuple},
+    IResult,
+};
 
 #[derive(Debug, Clone)]
 pub struct Day5 {
-    input: Input,
+    input: Almanac<'static>,
+    context: Context,
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
-struct Range {
-    a: usize,
-    b: usize,
+type Map = PiecewiseMap<usize>;
+
+// An `X-to-Y map:` section of the almanac, with its `(dest, source, len)`
+// entries as written.
+#[derive(Debug, Clone)]
+struct Stage<'a> {
+    from: &'a str,
+    to: &'a str,
+    entries: Vec<(usize, usize, usize)>,
 }
 
-impl Range {
-    fn contains(self, value: usize) -> bool {
-        self.a <= value && value <= self.b
-    }
+// The categories are the nodes of `graph`; every `X-to-Y map:` section is an
+// edge from X to Y whose weight is the index of its map in `maps`.
+#[derive(Debug, Clone)]
+struct Almanac<'a> {
+    seeds: Vec<usize>,
+    graph: Graph<&'a str>,
+    maps: Vec<Map>,
+}
 
-    fn intersect(self, other: Range) -> Option<Range> {
-        let left = self.a.max(other.a);
-        let right = self.b.min(other.b);
-        if left <= right {
-            Some(Range { a: left, b: right })
-        } else {
-            None
+impl<'a> Almanac<'a> {
+    fn new() -> Almanac<'a> {
+        Almanac {
+            seeds: Vec::new(),
+            graph: Graph::directed(),
+            maps: Vec::new(),
         }
     }
 
-    fn apply(self, map: &Map) -> HashSet<Range> {
-        let mut result: HashSet<Range> = HashSet::new();
-        for (from, to) in map {
-            let range = self.intersect(*from);
-            if let Some(range) = range {
-                result.insert(Range {
-                    a: to.a + range.a - from.a,
-                    b: to.b + range.b - from.b,
-                });
-            }
+    fn add_stage(&mut self, stage: Stage<'a>) -> Result<(), String> {
+        let i = self.graph.intern(stage.from);
+        let j = self.graph.intern(stage.to);
+        if self.graph.weight(i, j).is_some() {
+            return Err(format!(
+                "The {}-to-{} map is defined twice!",
+                stage.from, stage.to
+            ));
         }
-        result
+        let mut map = Map::new();
+        for (k, &(dest, source, len)) in stage.entries.iter().enumerate() {
+            let context =
+                |e: String| format!("{}-to-{} map, entry {}: {}", stage.from, stage.to, k + 1, e);
+            let source = Interval::with_len(source, len)
+                .ok_or(format!(
+                    "Source range {} + {} is empty or overflows!",
+                    source, len
+                ))
+                .map_err(context)?;
+            map.push(source, dest).map_err(context)?;
+        }
+        self.graph.add_edge_by_index(i, j, self.maps.len());
+        self.maps.push(map);
+        Ok(())
+    }
+
+    // The maps leading from category `from` to category `to`, in order.
+    fn chain(&self, from: &str, to: &str) -> Result<Vec<&Map>, String> {
+        let index = |category| {
+            self.graph
+                .index_of(&category)
+                .ok_or(format!("Unknown category {}!", category))
+        };
+        let path = self
+            .graph
+            .path(index(from)?, index(to)?)
+            .ok_or(format!("No chain of maps leads from {} to {}!", from, to))?;
+        Ok(path.iter().map(|&(_, _, k)| &self.maps[k]).collect())
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        self.chain("seed", "location").map(|_| ())
+    }
+
+    // All the maps from `from` to `to` folded into a single one.
+    fn compose(&self, from: &str, to: &str) -> Result<Map, String> {
+        Ok(self
+            .chain(from, to)?
+            .iter()
+            .fold(Map::new(), |composed, map| composed.then(map)))
+    }
+
+    fn convert(&self, from: &str, to: &str, value: usize) -> Result<usize, String> {
+        Ok(self
+            .chain(from, to)?
+            .iter()
+            .fold(value, |value, map| map.apply(value)))
+    }
+
+    fn convert_set(
+        &self,
+        from: &str,
+        to: &str,
+        set: IntervalSet<usize>,
+    ) -> Result<IntervalSet<usize>, String> {
+        Ok(self
+            .chain(from, to)?
+            .iter()
+            .fold(set, |set, map| map.apply_set(&set)))
     }
 }
 
-type Map = Vec<(Range, Range)>;
+fn parse_header(s: &str) -> IResult<&str, (&str, &str)> {
+    terminated(separated_pair(alpha1, tag("-to-"), alpha1), tag(" map:"))(s)
+}
 
-#[derive(Debug, Clone)]
-struct Input {
-    seeds: Vec<usize>,
-    maps: Vec<Map>,
+fn parse_entry(s: &str) -> IResult<&str, (usize, usize, usize)> {
+    map(
+        tuple((unsigned, space1, unsigned, space1, unsigned)),
+        |(dest, _, source, _, len)| (dest, source, len),
+    )(s)
 }
 
-impl Input {
-    fn new() -> Input {
-        let mut maps: Vec<Map> = Vec::new();
-        for _ in 0..7 {
-            maps.push(Vec::new());
-        }
-        Input {
-            seeds: Vec::new(),
-            maps,
-        }
-    }
+fn parse_stage(s: &str) -> IResult<&str, Stage<'_>> {
+    map(
+        separated_pair(
+            parse_header,
+            line_ending,
+            separated_list1(line_ending, parse_entry),
+        ),
+        |((from, to), entries)| Stage { from, to, entries },
+    )(s)
 }
 
 impl Day5 {
     pub fn new() -> Day5 {
         Day5 {
-            input: Input::new(),
+            input: Almanac::new(),
+            context: Context::default(),
         }
     }
 
     pub fn _clear(&mut self) {
-        self.input = Input::new();
+        self.input = Almanac::new();
+    }
+
+    fn seed_ranges(&self) -> IntervalSet<usize> {
+        self.input
+            .seeds
+            .chunks(2)
+            .filter(|pair| pair[1] > 0)
+            .map(|pair| {
+                Interval::with_len(pair[0], pair[1])
+                    .unwrap_or_else(|| panic!("Seed range {} + {} overflows!", pair[0], pair[1]))
+            })
+            .collect()
     }
 }
 
 impl Puzzle for Day5 {
+    fn configure(&mut self, context: &Context) {
+        self.context = context.clone();
+    }
+
     fn load_input(&mut self) {
         const INPUT: &str = include_str!("../inputs/5.input");
-        let mut current_map = 0;
-        for line in INPUT.lines() {
-            if line.is_empty() {
-                continue;
-            }
-            match &line[..5] {
-                "seeds" => {
-                    self.input.seeds = line[7..]
-                        .split(' ')
-                        .map(|seed| seed.parse::<usize>().unwrap())
-                        .collect::<Vec<_>>()
-                }
-                "seed-" => current_map = 0, //"seed_to_soil",
-                "soil-" => current_map = 1, //"soil_to_fertilizer",
-                "ferti" => current_map = 2, //"fertilizer_to_water",
-                "water" => current_map = 3, //"water_to_light",
-                "light" => current_map = 4, //"light_to_temperature",
-                "tempe" => current_map = 5, //"temperature_to_humidity",
-                "humid" => current_map = 6, //"humidity_to_location",
-                _ => {
-                    let mut tmp = line.split(' ');
-                    let a = tmp.next().unwrap().parse::<usize>().unwrap();
-                    let b = tmp.next().unwrap().parse::<usize>().unwrap();
-                    let c = tmp.next().unwrap().parse::<usize>().unwrap();
-                    self.input.maps[current_map]
-                        .push((Range { a: b, b: b + c - 1 }, Range { a, b: a + c - 1 }));
-                }
+        let blocks = blocks(INPUT);
+        let (line, seeds) = blocks[0];
+        self.input.seeds = parse_all(seeds, key_value(tag("seeds"), spaced(unsigned)))
+            .map_err(|e| e.offset_lines(line))
+            .unwrap()
+            .1;
+        for &(line, block) in &blocks[1..] {
+            let stage = parse_all(block, parse_stage)
+                .map_err(|e| e.offset_lines(line))
+                .unwrap();
+            if let Err(e) = self.input.add_stage(stage) {
+                panic!("{}", e);
             }
         }
-        for map in &mut self.input.maps {
-            map.sort_by(|(from1, _), (from2, _)| from1.a.cmp(&from2.a));
-            complete_range(map);
+        if let Err(e) = self.input.validate() {
+            panic!("{}", e);
         }
     }
 
@@ -118,64 +196,51 @@ impl Puzzle for Day5 {
             .input
             .seeds
             .iter()
-            .map(|seed| self.input.maps.iter().fold(*seed, map_value))
+            .map(|seed| self.input.convert("seed", "location", *seed).unwrap())
             .min()
             .unwrap();
         format!("{:?}", min_location)
     }
 
     fn part2(&self) -> String {
-        let mut ranges: HashSet<Range> = HashSet::new();
-        self.input.seeds.chunks(2).for_each(|pair| {
-            ranges.insert(Range {
-                a: pair[0],
-                b: pair[0] + pair[1] - 1,
-            });
-        });
-
-        self.input.maps.iter().for_each(|map| {
-            let mut new_ranges: HashSet<Range> = HashSet::new();
-            ranges
-                .iter()
-                .for_each(|range| new_ranges.extend(range.apply(map).iter()));
-            ranges = new_ranges;
-        });
-        let min_location = ranges.iter().map(|range| range.a).min().unwrap();
+        let ranges = self.seed_ranges();
+        let locations = self.input.convert_set("seed", "location", ranges).unwrap();
+        let min_location = locations.min().unwrap();
 
         format!("{:?}", min_location)
     }
-}
 
-fn map_value(value: usize, map: &Map) -> usize {
-    for (from, to) in map {
-        if from.contains(value) {
-            return to.a + value - from.a;
+    // The composed seed-to-location map, its inverse and the seed ranges that
+    // end up in the lowest `--param lowest=N` locations (1 by default).
+    fn details(&self) -> Vec<String> {
+        if !self.context.details {
+            return Vec::new();
         }
-    }
-    value
-}
-
-fn complete_range(map: &mut Map) {
-    let mut result: Vec<(Range, Range)> = Vec::new();
-    let mut edge = 0;
-    for (from, _) in &*map {
-        if from.a == 0 {
-            edge = from.b + 1;
-            continue;
+        let lowest = self
+            .context
+            .param("lowest")
+            .map_or(1, |n| n.parse::<u128>().unwrap());
+        let composed = self.input.compose("seed", "location").unwrap();
+        let mut rows = vec!["seed-to-location:".to_string()];
+        for (source, dest) in composed.pieces() {
+            rows.push(format!("    {} -> {}", source, dest));
         }
-        if edge < from.a {
-            let r = Range {
-                a: edge,
-                b: from.a - 1,
-            };
-            result.push((r, r));
+        match composed.inverse() {
+            Some(inverse) => {
+                rows.push("location-to-seed:".to_string());
+                for (source, dest) in inverse.pieces() {
+                    rows.push(format!("    {} -> {}", source, dest));
+                }
+            }
+            None => rows.push("location-to-seed: not invertible".to_string()),
         }
-        edge = from.b + 1;
+        let ranges = self.seed_ranges();
+        let locations = composed.apply_set(&ranges).lowest(lowest);
+        let seeds = composed.preimage(&locations).intersection(&ranges);
+        rows.push(format!(
+            "lowest {} locations {} come from seeds {}",
+            lowest, locations, seeds
+        ));
+        rows
     }
-    let r = Range {
-        a: edge,
-        b: usize::MAX,
-    };
-    result.push((r, r));
-    map.extend(result.iter());
 }
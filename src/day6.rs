@@ -1,60 +1,196 @@
-use aoc_2023_rust::Puzzle;
+use aoc_2023_rust::parse::{key_value, parse_lines, spaced};
+use aoc_2023_rust::{Context, Puzzle};
+use nom::character::complete::{alpha1, digit1};
+use num::integer::Roots;
+use num::{BigInt, Integer, ToPrimitive, Zero};
 
 #[derive(Debug, Clone)]
 pub struct Day6 {
-    input1: Vec<(i128, i128)>,
-    input2: (i128, i128),
+    input1: Vec<(BigInt, BigInt)>,
+    input2: (BigInt, BigInt),
+    context: Context,
 }
 
 impl Day6 {
     pub fn new() -> Day6 {
         Day6 {
             input1: Vec::new(),
-            input2: (0, 0),
+            input2: (BigInt::zero(), BigInt::zero()),
+            context: Context::default(),
         }
     }
 
     pub fn _clear(&mut self) {
         self.input1 = Vec::new();
-        self.input2 = (0, 0);
+        self.input2 = (BigInt::zero(), BigInt::zero());
     }
 }
 
 impl Puzzle for Day6 {
+    fn configure(&mut self, context: &Context) {
+        self.context = context.clone();
+    }
+
     fn load_input(&mut self) {
-        self.input1 = vec![(56, 546), (97, 1927), (78, 1131), (75, 1139)];
-        self.input2 = (56977875, 546192711311139);
+        const INPUT: &str = include_str!("../inputs/6.input");
+        let lines = parse_lines(INPUT, key_value(alpha1, spaced(digit1))).unwrap();
+        let (times, records) = match &lines[..] {
+            [("Time", times), ("Distance", records)] if times.len() == records.len() => {
+                (times, records)
+            }
+            _ => panic!("Expected matching Time and Distance lines!"),
+        };
+        self.input1 = times
+            .iter()
+            .zip(records.iter())
+            .map(|(t, r)| (t.parse::<BigInt>().unwrap(), r.parse::<BigInt>().unwrap()))
+            .collect();
+        self.input2 = (
+            times.concat().parse::<BigInt>().unwrap(),
+            records.concat().parse::<BigInt>().unwrap(),
+        );
     }
 
     fn part1(&self) -> String {
-        let n: i128 = self
+        let n: BigInt = self
             .input1
             .iter()
-            .map(|(total_time, record)| compute_interval_len(*total_time, *record))
+            .map(|(total_time, record)| compute_interval_len(total_time, record))
             .product();
         format!("{:?}", n)
     }
 
     fn part2(&self) -> String {
         let (total_time, record) = &self.input2;
-        let n = compute_interval_len(*total_time, *record);
+        let n = compute_interval_len(total_time, record);
         format!("{:?}", n)
     }
+
+    fn details(&self) -> Vec<String> {
+        if !self.context.details {
+            return Vec::new();
+        }
+        let races = self
+            .input1
+            .iter()
+            .enumerate()
+            .map(|(i, race)| (format!("race {}", i + 1), race))
+            .chain(std::iter::once(("long race".to_string(), &self.input2)));
+        races
+            .map(
+                |(name, (total_time, record))| match winning_holds(total_time, record) {
+                    Some((low, high)) => format!(
+                        "{}: hold for {}..={} ms ({} ways)",
+                        name,
+                        low,
+                        high,
+                        compute_interval_len(total_time, record)
+                    ),
+                    None => format!("{}: the record cannot be beaten", name),
+                },
+            )
+            .collect()
+    }
 }
 
-fn compute_interval_len(total_time: i128, record: i128) -> i128 {
-    let delta = total_time.pow(2) - 4 * record;
-    let left = (total_time as f64 - (delta as f64).sqrt()) / 2.0;
-    let right = (total_time as f64 + (delta as f64).sqrt()) / 2.0;
-    let l = if left.ceil() > left {
-        left.ceil() as i128
-    } else {
-        left.ceil() as i128 + 1
-    };
-    let r = if right.floor() < right {
-        right.floor() as i128
-    } else {
-        right.floor() as i128 - 1
-    };
-    r - l + 1
+fn compute_interval_len(total_time: &BigInt, record: &BigInt) -> BigInt {
+    match winning_holds(total_time, record) {
+        Some((low, high)) => high - low + 1,
+        None => BigInt::zero(),
+    }
+}
+
+// The first and last hold times that beat the record. Races that fit in an
+// i64 are solved in i128, where the discriminant cannot overflow; anything
+// larger falls back to BigInt.
+fn winning_holds(total_time: &BigInt, record: &BigInt) -> Option<(BigInt, BigInt)> {
+    match (total_time.to_i64(), record.to_i64()) {
+        (Some(t), Some(r)) => solve_race(&(t as i128), &(r as i128))
+            .map(|(low, high)| (BigInt::from(low), BigInt::from(high))),
+        _ => solve_race(total_time, record),
+    }
+}
+
+// Holding the button for h ms wins when h * (t - h) > r, i.e. when h lies
+// strictly between the roots (t -/+ sqrt(t^2 - 4r)) / 2. With s the integer
+// square root of the discriminant, (t - s) / 2 is within one of the first
+// winning time, and the winning times are symmetric around t / 2.
+fn solve_race<T: Integer + Roots + Clone>(total_time: &T, record: &T) -> Option<(T, T)> {
+    let two = T::one() + T::one();
+    let wins = |h: &T| h.clone() * (total_time.clone() - h.clone()) > *record;
+    if !wins(&total_time.div_floor(&two)) {
+        return None;
+    }
+    let delta =
+        total_time.clone() * total_time.clone() - two.clone() * two.clone() * record.clone();
+    let mut low = (total_time.clone() - delta.sqrt()).div_floor(&two) - T::one();
+    if low < T::zero() {
+        low = T::zero();
+    }
+    while !wins(&low) {
+        low = low + T::one();
+    }
+    let high = total_time.clone() - low.clone();
+    Some((low, high))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn brute_force(t: i64, r: i64) -> Option<(i64, i64)> {
+        let wins: Vec<i64> = (0..=t).filter(|h| h * (t - h) > r).collect();
+        Some((*wins.first()?, *wins.last()?))
+    }
+
+    #[test]
+    fn matches_brute_force_on_small_races() {
+        for t in 0..80 {
+            for r in 0..=(t * t / 4 + 2) {
+                let expected = brute_force(t, r).map(|(l, h)| (BigInt::from(l), BigInt::from(h)));
+                let holds = winning_holds(&BigInt::from(t), &BigInt::from(r));
+                assert_eq!(holds, expected, "t = {}, r = {}", t, r);
+                let len = expected.map_or(BigInt::zero(), |(l, h)| h - l + 1);
+                assert_eq!(
+                    compute_interval_len(&BigInt::from(t), &BigInt::from(r)),
+                    len
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn large_races_take_the_bigint_branch() {
+        let wins = |h: &BigInt, t: &BigInt, r: &BigInt| h * (t - h) > *r;
+        for (t, r) in [
+            ("9223372036854776807", "1234567890123456789012345"),
+            (
+                "9223372036854776807",
+                "21267647932558653966460912964485513215",
+            ),
+            ("123456789012345678901234567890", "98765432109876543210"),
+        ] {
+            let t = t.parse::<BigInt>().unwrap();
+            let r = r.parse::<BigInt>().unwrap();
+            assert!(t.to_i64().is_none());
+            let (low, high) = winning_holds(&t, &r).unwrap();
+            assert!(wins(&low, &t, &r) && !wins(&(&low - 1), &t, &r));
+            assert!(wins(&high, &t, &r) && !wins(&(&high + 1), &t, &r));
+            // Small enough for i128, which checks the BigInt arithmetic.
+            if let (Some(t), Some(r)) = (t.to_i128(), r.to_i128()) {
+                if t.checked_mul(t).is_some() {
+                    let (l, h) = solve_race(&t, &r).unwrap();
+                    assert_eq!((low, high), (BigInt::from(l), BigInt::from(h)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unbeatable_record() {
+        assert_eq!(winning_holds(&BigInt::from(7), &BigInt::from(12)), None);
+        let t = "100000000000000000000".parse::<BigInt>().unwrap();
+        let r = &t * &t / 4;
+        assert_eq!(winning_holds(&t, &r), None);
+    }
 }
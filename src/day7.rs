@@ -1,20 +1,88 @@
-use std::{cmp::Ordering, collections::HashMap};
+use std::cmp::Ordering;
+use std::time::Instant;
 
-use aoc_2023_rust::Puzzle;
+use aoc_2023_rust::{Context, Puzzle};
+use rand::Rng;
 
 #[derive(Debug, Clone)]
 pub struct Day7 {
     input: Vec<Hand>,
+    context: Context,
 }
 
 impl Day7 {
     pub fn new() -> Day7 {
-        Day7 { input: Vec::new() }
+        Day7 {
+            input: Vec::new(),
+            context: Context::default(),
+        }
     }
 
     pub fn _clear(&mut self) {
         self.input = Vec::new();
     }
+
+    // The rules for a part, given at runtime as e.g. `--param part2=joker` or
+    // `--param "part2=wild:J2,straight"`.
+    fn rules(&self, part: &str, default: &str) -> Rules {
+        Rules::parse(self.context.param(part).unwrap_or(default))
+    }
+
+    fn winnings(&self, part: usize) -> usize {
+        let mut hands: Vec<(u32, usize)> = self
+            .input
+            .iter()
+            .map(|hand| (hand.keys[part], hand.bid))
+            .collect();
+        hands.sort_unstable();
+        hands
+            .iter()
+            .enumerate()
+            .map(|(i, (_, bid))| (i + 1) * bid)
+            .sum()
+    }
+
+    // Sorts `n` random hands by comparing them on the fly and by precomputed
+    // keys, timing both and reporting where the two orders disagree.
+    fn bench(&self, n: usize) -> Vec<String> {
+        let rules = self.rules("part2", "joker");
+        let mut rng = self.context.rng();
+        let hands: Vec<Vec<char>> = (0..n)
+            .map(|_| {
+                (0..5)
+                    .map(|_| rules.order[rng.gen_range(0..rules.order.len())])
+                    .collect()
+            })
+            .collect();
+
+        let start = Instant::now();
+        let mut compared = hands.clone();
+        compared.sort_by(|a, b| rules.compare(a, b));
+        let by_comparison = start.elapsed();
+
+        let start = Instant::now();
+        let mut keyed: Vec<(u32, usize)> = hands
+            .iter()
+            .enumerate()
+            .map(|(i, cards)| (rules.key(cards), i))
+            .collect();
+        keyed.sort_unstable();
+        let by_key = start.elapsed();
+
+        let mut rows = vec![
+            format!("sorting {} hands by comparison: {:?}", n, by_comparison),
+            format!("sorting {} hands by precomputed key: {:?}", n, by_key),
+        ];
+        let mismatches = keyed
+            .iter()
+            .zip(&compared)
+            .filter(|&(&(_, i), cards)| rules.compare(&hands[i], cards) != Ordering::Equal)
+            .count();
+        if mismatches > 0 {
+            rows.push(format!("the two orders differ at {} places!", mismatches));
+        }
+        rows
+    }
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, PartialOrd, Ord)]
@@ -23,161 +91,210 @@ enum Rank {
     OnePair,
     TwoPairs,
     Three,
+    Straight,
     Full,
     Four,
     Five,
 }
 
+const RANKS: [Rank; 8] = [
+    Rank::HighCard,
+    Rank::OnePair,
+    Rank::TwoPairs,
+    Rank::Three,
+    Rank::Straight,
+    Rank::Full,
+    Rank::Four,
+    Rank::Five,
+];
+
+const STANDARD_ORDER: &str = "23456789TJQKA";
+
+// Card strengths are packed into 5 bits each.
+const MAX_CARDS: usize = 32;
+
+// Each hand carries its sort key under the part 1 and part 2 rules.
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct Hand {
-    hand: Vec<usize>,
-    stats: HashMap<usize, usize>,
     bid: usize,
+    keys: [u32; 2],
+}
+
+// A variant of Camel Cards: the cards from weakest to strongest, the wildcards
+// that stand in for whatever card makes the best hand and the hand categories
+// that count.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rules {
+    order: Vec<char>,
+    wildcards: Vec<char>,
+    categories: Vec<Rank>,
 }
 
-fn group(hand: &[usize]) -> HashMap<usize, usize> {
-    let mut hash: HashMap<usize, usize> = HashMap::new();
-    for card in hand {
-        if let Some(count) = hash.get_mut(card) {
-            *count += 1;
-        } else {
-            hash.insert(*card, 1);
+impl Rules {
+    fn standard() -> Rules {
+        Rules {
+            order: STANDARD_ORDER.chars().collect(),
+            wildcards: Vec::new(),
+            categories: RANKS
+                .into_iter()
+                .filter(|&rank| rank != Rank::Straight)
+                .collect(),
         }
     }
-    hash
-}
 
-impl Hand {
-    fn eval(&self) -> Rank {
-        let mut counts = self.stats.values().collect::<Vec<_>>();
-        counts.sort_by(|a, b| b.cmp(a));
-        match counts[0] {
-            5 => Rank::Five,
-            4 => Rank::Four,
-            3 => {
-                if *counts[1] == 2 {
-                    Rank::Full
-                } else {
-                    Rank::Three
-                }
-            }
-            2 => {
-                if *counts[1] == 2 {
-                    Rank::TwoPairs
-                } else {
-                    Rank::OnePair
-                }
-            }
-            1 => Rank::HighCard,
-            _ => panic!("How many cards did you get?"),
+    // Wildcards are the weakest cards when hands are compared card by card.
+    fn with_wildcards(mut self, wildcards: &str) -> Rules {
+        self.wildcards = wildcards.chars().collect();
+        self.order.retain(|c| !self.wildcards.contains(c));
+        self.order.splice(0..0, self.wildcards.iter().copied());
+        self
+    }
+
+    // A comma-separated list of `standard`, `joker`, `wild:<cards>` and
+    // `straight`, applied in order.
+    fn parse(spec: &str) -> Rules {
+        let mut rules = Rules::standard();
+        for part in spec.split(',').map(|s| s.trim()) {
+            rules = match part.split_once(':') {
+                Some(("wild", cards)) => rules.with_wildcards(cards),
+                _ => match part {
+                    "standard" => Rules::standard(),
+                    "joker" => rules.with_wildcards("J"),
+                    "straight" => {
+                        rules.categories.push(Rank::Straight);
+                        rules.categories.sort();
+                        rules
+                    }
+                    _ => panic!("Unknown rule {}!", part),
+                },
+            };
+        }
+        if rules.order.len() > MAX_CARDS {
+            panic!("At most {} different cards are supported!", MAX_CARDS);
         }
+        rules
     }
 
-    fn adjust(&self) -> Self {
-        let new_hand = self
-            .hand
+    fn strength(&self, card: char) -> usize {
+        self.order
             .iter()
-            .map(|&card| if card == 11 { 0 } else { card })
-            .collect();
+            .position(|&c| c == card)
+            .unwrap_or_else(|| panic!("What is this card: {}?", card))
+    }
 
-        let mut new_stats: HashMap<usize, usize> = self.stats.clone();
-        if let Some(count) = new_stats.remove(&11) {
-            let mut max_key = None;
-            let mut max_count = 0;
-            for (&card, &count) in &new_stats {
-                if count > max_count {
-                    max_count = count;
-                    max_key = Some(card);
-                }
-            }
-            if let Some(max_key) = max_key {
-                if let Some(x) = new_stats.get_mut(&max_key) {
-                    *x += count;
-                }
+    // The best category the cards can make, wildcards included.
+    fn rank(&self, cards: &[char]) -> Rank {
+        let mut stats = [0; MAX_CARDS];
+        let mut wild = 0;
+        for &card in cards {
+            if self.wildcards.contains(&card) {
+                wild += 1;
             } else {
-                new_stats.insert(11, 5);
+                stats[self.strength(card)] += 1;
             }
         }
-        Hand {
-            hand: new_hand,
-            stats: new_stats,
-            bid: self.bid,
+        let mut counts = [0; 2];
+        for &count in &stats {
+            if count > counts[0] {
+                counts = [count, counts[0]];
+            } else if count > counts[1] {
+                counts[1] = count;
+            }
         }
+        let short = |needs: &[usize]| -> usize {
+            needs
+                .iter()
+                .zip(&counts)
+                .map(|(need, count)| need.saturating_sub(*count))
+                .sum()
+        };
+        let possible = |rank: Rank| match rank {
+            Rank::Five => short(&[5]) <= wild,
+            Rank::Four => short(&[4]) <= wild,
+            Rank::Full => short(&[3, 2]) <= wild,
+            Rank::Straight => self.straight(&stats),
+            Rank::Three => short(&[3]) <= wild,
+            Rank::TwoPairs => short(&[2, 2]) <= wild,
+            Rank::OnePair => short(&[2]) <= wild,
+            Rank::HighCard => true,
+        };
+        *self
+            .categories
+            .iter()
+            .rev()
+            .find(|&&rank| possible(rank))
+            .unwrap_or(&Rank::HighCard)
     }
-}
 
-impl Ord for Hand {
-    fn cmp(&self, other: &Self) -> Ordering {
-        let self_rank = self.eval();
-        let other_rank = other.eval();
-        if self_rank == other_rank {
-            for (x, y) in self.hand.iter().zip(other.hand.iter()) {
-                if x != y {
-                    return x.cmp(y);
-                }
-            }
-            Ordering::Equal
-        } else {
-            self_rank.cmp(&other_rank)
+    // Five cards of consecutive strength, not counting the wildcards, which
+    // fill any gaps.
+    fn straight(&self, stats: &[usize]) -> bool {
+        let naturals = self.order.len() - self.wildcards.len();
+        if stats.iter().any(|&count| count > 1) || naturals < 5 {
+            return false;
+        }
+        let low = stats.iter().position(|&count| count > 0);
+        let high = stats.iter().rposition(|&count| count > 0);
+        match (low, high) {
+            (Some(low), Some(high)) => high - low < 5,
+            _ => true,
         }
     }
-}
 
-impl PartialOrd for Hand {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+    // How the puzzle orders hands: by category first, then card by card.
+    fn compare(&self, a: &[char], b: &[char]) -> Ordering {
+        let strengths = |cards: &[char]| -> Vec<usize> {
+            cards.iter().map(|&card| self.strength(card)).collect()
+        };
+        (self.rank(a), strengths(a)).cmp(&(self.rank(b), strengths(b)))
+    }
+
+    // Hands sort by this key, weakest first: the rank in the top bits followed
+    // by the strength of each card in turn.
+    fn key(&self, cards: &[char]) -> u32 {
+        cards.iter().fold(self.rank(cards) as u32, |key, &card| {
+            (key << 5) | self.strength(card) as u32
+        })
     }
 }
 
 impl Puzzle for Day7 {
+    fn configure(&mut self, context: &Context) {
+        self.context = context.clone();
+    }
+
     fn load_input(&mut self) {
         const INPUT: &str = include_str!("../inputs/7.input");
+        let rules = [
+            self.rules("part1", "standard"),
+            self.rules("part2", "joker"),
+        ];
         for line in INPUT.lines() {
             let (hand_str, bid_str) = line.split_once(' ').unwrap();
             let bid = bid_str.parse::<usize>().unwrap();
-            let hand: Vec<usize> = hand_str
-                .chars()
-                .map(|c| {
-                    if c.is_ascii_digit() {
-                        c.to_digit(10).unwrap() as usize
-                    } else {
-                        match c {
-                            'T' => 10,
-                            'J' => 11,
-                            'Q' => 12,
-                            'K' => 13,
-                            'A' => 14,
-                            _ => panic!("What is this card?"),
-                        }
-                    }
-                })
-                .collect();
-            let stats = group(&hand);
-            self.input.push(Hand { hand, stats, bid });
+            let cards: Vec<char> = hand_str.chars().collect();
+            if cards.len() != 5 {
+                panic!("How many cards did you get?");
+            }
+            let keys = [rules[0].key(&cards), rules[1].key(&cards)];
+            self.input.push(Hand { bid, keys });
         }
     }
 
     fn part1(&self) -> String {
-        let mut hands = self.input.clone();
-        hands.sort();
-        let win: usize = hands
-            .iter()
-            .enumerate()
-            .map(|(i, hand)| (i + 1) * hand.bid)
-            .sum();
-
+        let win = self.winnings(0);
         format!("{:?}", win)
     }
 
     fn part2(&self) -> String {
-        let mut hands: Vec<Hand> = self.input.iter().map(|hand| hand.adjust()).collect();
-        hands.sort();
-        let win: usize = hands
-            .iter()
-            .enumerate()
-            .map(|(i, hand)| (i + 1) * hand.bid)
-            .sum();
-
+        let win = self.winnings(1);
         format!("{:?}", win)
     }
+
+    fn details(&self) -> Vec<String> {
+        match self.context.param("bench") {
+            Some(n) => self.bench(n.parse::<usize>().unwrap()),
+            None => Vec::new(),
+        }
+    }
 }
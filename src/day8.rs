@@ -1,17 +1,26 @@
-use num::Integer;
-use num_bigint::BigUint;
-use std::collections::HashMap;
-use std::fmt::Debug;
+use num::bigint::BigInt;
+use num::{Integer, One, Zero};
+use std::collections::BTreeMap;
+use std::fmt::{Debug, Display};
 use std::hash::Hash;
 
-use aoc_2023_rust::Puzzle;
+use aoc_2023_rust::graph::Graph;
+use aoc_2023_rust::parse::{blocks, parse_lines};
+use aoc_2023_rust::{Context, Puzzle};
+use nom::{
+    bytes::complete::tag,
+    character::complete::alphanumeric1,
+    sequence::{delimited, separated_pair},
+    IResult,
+};
 
 #[derive(Debug, Clone)]
 pub struct Day8 {
     input: Input<'static, Node<'static>>,
+    context: Context,
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
 struct Input<'a, N> {
     directions: &'a str,
     map: Graph<N>,
@@ -19,74 +28,354 @@ struct Input<'a, N> {
 
 type Node<'a> = &'a str;
 
-#[derive(Debug, Clone, Default)]
-struct Graph<N> {
-    neighbors: HashMap<N, (N, N)>,
-}
+impl<'a, N: Clone + Eq + Hash + Debug> Input<'a, N> {
+    fn new() -> Self {
+        Input {
+            directions: "",
+            map: Graph::directed(),
+        }
+    }
+
+    // Every node has two outgoing edges: left first, then right.
+    fn next(&self, node: usize) -> (usize, usize) {
+        let nei = self.map.neighbors(node);
+        (nei[0].0, nei[1].0)
+    }
+
+    fn validate(&self) -> Result<(), String>
+    where
+        N: Display,
+    {
+        if self.directions.is_empty() {
+            return Err("There are no directions!".to_string());
+        }
+        if let Some((i, c)) = self
+            .directions
+            .char_indices()
+            .find(|&(_, c)| c != 'L' && c != 'R')
+        {
+            return Err(format!("Direction {} is {:?}, not L or R!", i + 1, c));
+        }
+        for (i, node) in self.map.nodes().iter().enumerate() {
+            match self.map.neighbors(i).len() {
+                0 => return Err(format!("Node {} is used but never defined!", node)),
+                2 => {}
+                _ => return Err(format!("Node {} is defined more than once!", node)),
+            }
+        }
+        Ok(())
+    }
+
+    // Why `to` can never be reached from `from`, if it can't.
+    fn route_error(&self, from: &N, to: &N) -> Option<String>
+    where
+        N: Display,
+    {
+        match (self.map.index_of(from), self.map.index_of(to)) {
+            (None, _) => Some(format!("there is no node {}", from)),
+            (_, None) => Some(format!("there is no node {}", to)),
+            (Some(i), Some(j)) if !self.map.reachable(i)[j] => {
+                Some(format!("{} cannot be reached from {}", to, from))
+            }
+            _ => None,
+        }
+    }
 
-impl<'a, N: Eq + Hash + Debug> Input<'a, N> {
-    fn traverse<F>(&self, start: N, finished: F) -> usize
+    // Follows the directions from `start` until a (node, instruction) state
+    // comes round again, noting every step that lands on a finished node.
+    fn walk<F>(&self, start: usize, finished: F) -> Walk
     where
         F: Fn(&N) -> bool,
     {
-        let mut current = &start;
-        for (steps, dir) in self.directions.chars().cycle().enumerate() {
-            if finished(current) {
-                return steps;
-            } else {
-                current = if dir == 'L' {
-                    &self.map.neighbors[&current].0
-                } else {
-                    &self.map.neighbors[&current].1
+        let len = self.directions.len();
+        let directions = self.directions.as_bytes();
+        let mut seen: Vec<Option<usize>> = vec![None; self.map.len() * len];
+        let mut hits = Vec::new();
+        let mut current = start;
+        for step in 0.. {
+            let state = current * len + step % len;
+            if let Some(prefix) = seen[state] {
+                let (transient, cyclic) = hits.iter().partition(|&&hit| hit < prefix);
+                return Walk {
+                    prefix,
+                    period: step - prefix,
+                    transient,
+                    cyclic,
                 };
             }
+            seen[state] = Some(step);
+            if finished(self.map.node(current)) {
+                hits.push(step);
+            }
+            let (left, right) = self.next(current);
+            current = if directions[step % len] == b'L' {
+                left
+            } else {
+                right
+            };
         }
-        usize::MAX
+        unreachable!()
+    }
+}
+
+// Where a walk finishes: at the `transient` steps, which happen once, and at
+// every step `offset + k * period` for an `offset` in `cyclic` and k >= 0.
+// The walk enters its cycle after `prefix` steps.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Walk {
+    prefix: usize,
+    period: usize,
+    transient: Vec<usize>,
+    cyclic: Vec<usize>,
+}
+
+impl Walk {
+    fn finishes_at(&self, step: &BigInt) -> bool {
+        self.transient.iter().any(|&hit| BigInt::from(hit) == *step)
+            || self.cyclic.iter().any(|&offset| {
+                let offset = BigInt::from(offset);
+                *step >= offset && ((step - offset) % self.period).is_zero()
+            })
+    }
+
+    fn first(&self) -> Option<usize> {
+        self.transient.first().or(self.cyclic.first()).copied()
     }
 }
 
+// Solves x = a1 (mod m1), x = a2 (mod m2), returning (x, lcm(m1, m2)).
+fn crt((a1, m1): (BigInt, BigInt), (a2, m2): (BigInt, BigInt)) -> Option<(BigInt, BigInt)> {
+    let gcd = m1.extended_gcd(&m2);
+    let diff = &a2 - &a1;
+    if !(&diff % &gcd.gcd).is_zero() {
+        return None;
+    }
+    let lcm = &m1 / &gcd.gcd * &m2;
+    let x = (a1 + &m1 * (diff / &gcd.gcd * gcd.x)).mod_floor(&lcm);
+    Some((x, lcm))
+}
+
+// The first step at which every walk finishes together, if there is one. Such
+// a step is either one of the transient hits of some walk or lies in the cycle
+// of every walk, in which case it solves a system of congruences.
+fn simultaneous(walks: &[Walk]) -> Option<BigInt> {
+    let mut best: Option<BigInt> = None;
+    let mut consider = |step: BigInt| {
+        if best.as_ref().is_none_or(|best| step < *best) {
+            best = Some(step);
+        }
+    };
+    for walk in walks {
+        for &hit in &walk.transient {
+            let step = BigInt::from(hit);
+            if walks.iter().all(|w| w.finishes_at(&step)) {
+                consider(step);
+            }
+        }
+    }
+    // The steps in the cycle of every walk seen so far, as residues modulo the
+    // lcm of their periods, each valid from the first step that is past the
+    // offsets it was built from. Residues reached from several choices of
+    // offsets keep the earliest such step.
+    let mut modulus = BigInt::one();
+    let mut residues = BTreeMap::from([(BigInt::zero(), BigInt::zero())]);
+    for walk in walks {
+        let period = BigInt::from(walk.period);
+        let mut next: BTreeMap<BigInt, BigInt> = BTreeMap::new();
+        for (residue, lowest) in &residues {
+            for &offset in &walk.cyclic {
+                let offset = BigInt::from(offset);
+                let congruence = (residue.clone(), modulus.clone());
+                if let Some((x, _)) = crt(congruence, (offset.clone(), period.clone())) {
+                    let lowest = lowest.max(&offset).clone();
+                    let entry = next.entry(x).or_insert_with(|| lowest.clone());
+                    if lowest < *entry {
+                        *entry = lowest;
+                    }
+                }
+            }
+        }
+        modulus = modulus.lcm(&period);
+        residues = next;
+    }
+    for (x, lowest) in residues {
+        // The smallest solution that is past every offset.
+        let step = if x >= lowest {
+            x
+        } else {
+            &x + (&lowest - &x).div_ceil(&modulus) * &modulus
+        };
+        consider(step);
+    }
+    best
+}
+
+fn parse_node(s: &str) -> IResult<&str, (&str, (&str, &str))> {
+    separated_pair(
+        alphanumeric1,
+        tag(" = "),
+        delimited(
+            tag("("),
+            separated_pair(alphanumeric1, tag(", "), alphanumeric1),
+            tag(")"),
+        ),
+    )(s)
+}
+
 impl Day8 {
     pub fn new() -> Day8 {
         Day8 {
-            input: Input::default(),
+            input: Input::new(),
+            context: Context::default(),
         }
     }
 
     pub fn _clear(&mut self) {
-        self.input = Input::default()
+        self.input = Input::new()
+    }
+
+    fn ghosts(&self) -> Vec<(usize, Walk)> {
+        let finished = |node: &Node<'static>| node.ends_with('Z');
+        self.input
+            .map
+            .nodes()
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| node.ends_with('A'))
+            .map(|(start, _)| (start, self.input.walk(start, finished)))
+            .collect()
     }
 }
 
 impl Puzzle for Day8 {
+    fn configure(&mut self, context: &Context) {
+        self.context = context.clone();
+    }
+
     fn load_input(&mut self) {
         const INPUT: &str = include_str!("../inputs/8.input");
-        let mut lines = INPUT.lines();
-        self.input.directions = lines.next().unwrap();
-        lines.next();
-        for line in lines {
-            let (node, pair) = line.split_once(" = ").unwrap();
-            let (left, right) = pair[1..(pair.len() - 1)].split_once(", ").unwrap();
-            self.input.map.neighbors.insert(node, (left, right));
+        let blocks = blocks(INPUT);
+        self.input.directions = blocks[0].1.trim();
+        let (line, network) = blocks[1];
+        let nodes = parse_lines(network, parse_node)
+            .map_err(|e| e.offset_lines(line))
+            .unwrap();
+        for (node, (left, right)) in nodes {
+            self.input.map.add_edge(node, left, 1);
+            self.input.map.add_edge(node, right, 1);
+        }
+        if let Err(e) = self.input.validate() {
+            panic!("{}", e);
         }
     }
 
     fn part1(&self) -> String {
-        let start = "AAA";
-        let finished = |node: &Node<'static>| (*node == "ZZZ");
-        let steps = self.input.traverse(start, finished);
-        format!("{:?}", steps)
+        if let Some(e) = self.input.route_error(&"AAA", &"ZZZ") {
+            return e;
+        }
+        let start = self.input.map.index_of(&"AAA").unwrap();
+        let finished = |node: &Node<'static>| *node == "ZZZ";
+        match self.input.walk(start, finished).first() {
+            Some(steps) => format!("{:?}", steps),
+            None => "never".to_string(),
+        }
     }
 
     fn part2(&self) -> String {
-        let finished = |node: &Node<'static>| node.ends_with('Z');
-        let steps = self
-            .input
-            .map
-            .neighbors
-            .keys()
-            .filter(|node| node.ends_with('A'))
-            .map(|node| BigUint::from(self.input.traverse(node, finished)))
-            .fold(BigUint::from(1usize), |acc, s| acc.lcm(&s));
-        format!("{:?}", steps)
+        let walks: Vec<Walk> = self.ghosts().into_iter().map(|(_, walk)| walk).collect();
+        match simultaneous(&walks) {
+            Some(steps) => format!("{}", steps),
+            None => "never".to_string(),
+        }
+    }
+
+    fn details(&self) -> Vec<String> {
+        if !self.context.details {
+            return Vec::new();
+        }
+        let map = &self.input.map;
+        let mut rows = Vec::new();
+        for (start, walk) in self.ghosts() {
+            let reachable = map.reachable(start);
+            let ends = map
+                .nodes()
+                .iter()
+                .enumerate()
+                .filter(|&(i, node)| node.ends_with('Z') && reachable[i])
+                .map(|(_, node)| *node)
+                .collect::<Vec<_>>();
+            rows.push(format!(
+                "{}: can reach [{}]; cycle of {} steps after {}, finishing at {:?} and then at {:?} every cycle",
+                map.node(start),
+                ends.join(", "),
+                walk.period,
+                walk.prefix,
+                walk.transient,
+                walk.cyclic
+            ));
+        }
+        rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn random_walk<R: Rng>(rng: &mut R) -> Walk {
+        let prefix = rng.gen_range(0..6);
+        let period = rng.gen_range(1..8);
+        let transient = (0..prefix).filter(|_| rng.gen_bool(0.3)).collect();
+        let cyclic = (prefix..prefix + period)
+            .filter(|_| rng.gen_bool(0.4))
+            .collect();
+        Walk {
+            prefix,
+            period,
+            transient,
+            cyclic,
+        }
+    }
+
+    #[test]
+    fn matches_step_by_step_search() {
+        let mut rng = StdRng::seed_from_u64(8);
+        for _ in 0..2000 {
+            let walks: Vec<Walk> = (0..rng.gen_range(1..5))
+                .map(|_| random_walk(&mut rng))
+                .collect();
+            // Past every prefix, the walks repeat together every lcm of the
+            // periods steps.
+            let horizon = walks.iter().map(|w| w.prefix).max().unwrap()
+                + walks.iter().fold(1, |l, w| l.lcm(&w.period));
+            let expected = (0..horizon)
+                .map(BigInt::from)
+                .find(|step| walks.iter().all(|w| w.finishes_at(step)));
+            assert_eq!(simultaneous(&walks), expected, "{:?}", walks);
+        }
+    }
+
+    // Eight walks with 30 offsets each would be 30^8 systems on their own,
+    // but they share the period and so have at most 60 residues.
+    #[test]
+    fn many_offsets_per_cycle() {
+        let walks: Vec<Walk> = (0..8)
+            .map(|k| Walk {
+                prefix: 3,
+                period: 60,
+                transient: Vec::new(),
+                cyclic: (3..63).filter(|step| (step + k) % 2 == 0).collect(),
+            })
+            .collect();
+        assert_eq!(simultaneous(&walks), None);
+        let walks: Vec<Walk> = (0..8)
+            .map(|k| Walk {
+                cyclic: (3..63).filter(|step| step % (k + 2) != 0).collect(),
+                ..walks[0].clone()
+            })
+            .collect();
+        assert_eq!(simultaneous(&walks), Some(BigInt::from(11)));
     }
 }
@@ -1,97 +1,186 @@
-use aoc_2023_rust::Puzzle;
+use aoc_2023_rust::parse::{parse_lines, signed, spaced};
+use aoc_2023_rust::{Context, Puzzle};
+use num::bigint::BigInt;
+use num::rational::BigRational;
+use num::{One, Signed, Zero};
 
 #[derive(Debug, Clone)]
 pub struct Day9 {
-    input: Input,
-}
-
-#[derive(Debug, Clone, Default)]
-struct Input {
-    histories: Vec<History>,
-    predictions: Vec<(isize, isize)>,
+    input: Vec<History>,
+    context: Context,
 }
 
+// A sequence together with the first value of each of its difference rows,
+// down to the first all-zero row, or `None` when the rows run out before any
+// of them is all zeros.
 #[derive(Debug, Clone)]
 struct History {
     seq: Vec<isize>,
+    leading: Option<Vec<BigInt>>,
 }
 
-type Summary = Vec<(isize, isize)>;
-
 impl Day9 {
     pub fn new() -> Day9 {
         Day9 {
-            input: Input::default(),
+            input: Vec::new(),
+            context: Context::default(),
         }
     }
 
     pub fn _clear(&mut self) {
-        self.input = Input::default()
+        self.input = Vec::new()
     }
+
+    // How far to extrapolate, given at runtime as e.g. `--param steps=10`.
+    fn steps(&self) -> isize {
+        self.context
+            .param("steps")
+            .map_or(1, |n| n.parse::<isize>().unwrap())
+    }
+
+    // The sum of the values `offset` places from the start of every sequence,
+    // or a message about the first sequence that cannot be extrapolated.
+    fn total(&self, offset: impl Fn(&History) -> isize) -> String {
+        let mut total = BigInt::zero();
+        for (i, history) in self.input.iter().enumerate() {
+            match history.value_at(offset(history)) {
+                Some(value) => total += value,
+                None => return format!("line {} never reaches all zeros", i + 1),
+            }
+        }
+        format!("{}", total)
+    }
+}
+
+fn diffs(seq: &[BigInt]) -> Vec<BigInt> {
+    seq.windows(2).map(|w| &w[1] - &w[0]).collect()
 }
 
 impl History {
-    fn diffs(self) -> Self {
+    fn new(seq: Vec<isize>) -> Self {
+        let mut row: Vec<BigInt> = seq.iter().map(|&n| BigInt::from(n)).collect();
+        let mut leading = Vec::new();
+        while !row.iter().all(|n| n.is_zero()) {
+            if row.len() == 1 {
+                return History { seq, leading: None };
+            }
+            leading.push(row[0].clone());
+            row = diffs(&row);
+        }
         History {
-            seq: self
-                .seq
-                .iter()
-                .skip(1)
-                .zip(self.seq.iter())
-                .map(|(b, a)| b - a)
-                .collect(),
+            seq,
+            leading: Some(leading),
+        }
+    }
+
+    // The value at position `x`, counting from 0 at the first element; any
+    // integer works, negative ones included. This is Newton's forward
+    // difference formula: the sum of the k-th leading difference times
+    // binomial(x, k).
+    fn value_at(&self, x: isize) -> Option<BigInt> {
+        let x = BigInt::from(x);
+        let mut binomial = BigInt::one();
+        let mut value = BigInt::zero();
+        for (k, diff) in self.leading.as_ref()?.iter().enumerate() {
+            value += diff * &binomial;
+            binomial = binomial * (&x - k) / (k + 1);
         }
+        Some(value)
     }
 
-    fn create_summary(self) -> Summary {
-        let mut seq = self.seq;
-        let mut summary = vec![(seq[0], seq[seq.len() - 1])];
-        while !seq.iter().all(|&n| n == 0) {
-            let new_seq = History { seq }.diffs().seq;
-            summary.push((new_seq[0], new_seq[new_seq.len() - 1]));
-            seq = new_seq;
+    // The coefficients of the fitted polynomial in `x`, constant term first.
+    fn coefficients(&self) -> Option<Vec<BigRational>> {
+        let leading = self.leading.as_ref()?;
+        let mut coefficients = vec![BigRational::zero(); leading.len()];
+        // x (x - 1) ... (x - k + 1) / k!, constant term first.
+        let mut falling = vec![BigRational::one()];
+        for (k, diff) in leading.iter().enumerate() {
+            let diff = BigRational::from(diff.clone());
+            for (c, f) in coefficients.iter_mut().zip(&falling) {
+                *c += &diff * f;
+            }
+            let k = BigRational::from(BigInt::from(k));
+            let mut next = vec![BigRational::zero(); falling.len() + 1];
+            for (i, f) in falling.iter().enumerate() {
+                next[i + 1] += f / (&k + BigRational::one());
+                next[i] -= f * &k / (&k + BigRational::one());
+            }
+            falling = next;
         }
-        summary
+        Some(coefficients)
     }
+}
 
-    fn predict(summary: &Summary) -> (isize, isize) {
-        let mut current_first = 0;
-        let mut current_last = 0;
-        for (first, last) in summary.iter().rev().skip(1) {
-            current_first = first - current_first;
-            current_last += last;
+fn polynomial(coefficients: &[BigRational]) -> String {
+    let mut terms = Vec::new();
+    for (i, c) in coefficients.iter().enumerate().rev() {
+        if c.is_zero() {
+            continue;
+        }
+        let sign = if c.is_negative() { "-" } else { "+" };
+        let c = c.abs();
+        let factor = if c.is_one() && i > 0 {
+            String::new()
+        } else {
+            format!("{}", c)
+        };
+        let power = match i {
+            0 => String::new(),
+            1 => "x".to_string(),
+            _ => format!("x^{}", i),
+        };
+        terms.push((sign, format!("{}{}", factor, power)));
+    }
+    let mut s = String::new();
+    for (i, (sign, term)) in terms.iter().enumerate() {
+        match (i, *sign) {
+            (0, "+") => {}
+            (0, _) => s.push('-'),
+            _ => s.push_str(&format!(" {} ", sign)),
         }
-        (current_first, current_last)
+        s.push_str(term);
     }
+    if s.is_empty() {
+        s.push('0');
+    }
+    s
 }
 
 impl Puzzle for Day9 {
+    fn configure(&mut self, context: &Context) {
+        self.context = context.clone();
+    }
+
     fn load_input(&mut self) {
         const INPUT: &str = include_str!("../inputs/9.input");
-        for line in INPUT.lines() {
-            let seq: Vec<isize> = line
-                .split(' ')
-                .map(|s| s.parse::<isize>().unwrap())
-                .collect();
-            self.input.histories.push(History { seq });
-        }
-        self.input.predictions = self
-            .input
-            .histories
-            .iter()
-            .map(|history| History::predict(&history.clone().create_summary()))
+        self.input = parse_lines(INPUT, spaced(signed))
+            .unwrap()
+            .into_iter()
+            .map(History::new)
             .collect();
     }
 
     fn part1(&self) -> String {
-        let result: isize = self.input.predictions.iter().map(|(_, x)| x).sum();
-
-        format!("{:?}", result)
+        let steps = self.steps();
+        self.total(|history| history.seq.len() as isize - 1 + steps)
     }
 
     fn part2(&self) -> String {
-        let result: isize = self.input.predictions.iter().map(|(x, _)| x).sum();
+        let steps = self.steps();
+        self.total(|_| -steps)
+    }
 
-        format!("{:?}", result)
+    fn details(&self) -> Vec<String> {
+        if !self.context.details {
+            return Vec::new();
+        }
+        self.input
+            .iter()
+            .enumerate()
+            .map(|(i, history)| match history.coefficients() {
+                Some(coefficients) => format!("line {}: {}", i + 1, polynomial(&coefficients)),
+                None => format!("line {}: never reaches all zeros", i + 1),
+            })
+            .collect()
     }
 }
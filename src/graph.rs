@@ -0,0 +1,514 @@
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::fmt::{Display, Write};
+use std::hash::Hash;
+
+/// A graph over interned nodes of type `N` with weighted edges.
+///
+/// Nodes are numbered in the order they are first seen and every method works
+/// with those indices; `index_of` and `node` translate back and forth. Edges
+/// are kept in insertion order and parallel edges are allowed.
+#[derive(Debug, Clone)]
+pub struct Graph<N> {
+    directed: bool,
+    nodes: Vec<N>,
+    index: HashMap<N, usize>,
+    adj: Vec<Vec<(usize, usize)>>,
+}
+
+/// A minimum cut of an undirected graph: the total weight of the cut edges, the
+/// edges themselves and the two sides of the partition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MinCut {
+    pub weight: usize,
+    pub edges: Vec<(usize, usize)>,
+    pub partition: (Vec<usize>, Vec<usize>),
+}
+
+impl<N: Clone + Eq + Hash> Graph<N> {
+    pub fn directed() -> Self {
+        Graph {
+            directed: true,
+            nodes: Vec::new(),
+            index: HashMap::new(),
+            adj: Vec::new(),
+        }
+    }
+
+    pub fn undirected() -> Self {
+        Graph {
+            directed: false,
+            ..Graph::directed()
+        }
+    }
+
+    pub fn is_directed(&self) -> bool {
+        self.directed
+    }
+
+    /// Returns the index of `node`, adding it to the graph if it is new.
+    pub fn intern(&mut self, node: N) -> usize {
+        if let Some(&i) = self.index.get(&node) {
+            return i;
+        }
+        let i = self.nodes.len();
+        self.nodes.push(node.clone());
+        self.index.insert(node, i);
+        self.adj.push(Vec::new());
+        i
+    }
+
+    pub fn index_of(&self, node: &N) -> Option<usize> {
+        self.index.get(node).copied()
+    }
+
+    pub fn node(&self, i: usize) -> &N {
+        &self.nodes[i]
+    }
+
+    pub fn nodes(&self) -> &[N] {
+        &self.nodes
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Adds an edge between `from` and `to`, interning both, and returns their
+    /// indices. Undirected edges are stored in both adjacency lists.
+    pub fn add_edge(&mut self, from: N, to: N, weight: usize) -> (usize, usize) {
+        let i = self.intern(from);
+        let j = self.intern(to);
+        self.add_edge_by_index(i, j, weight);
+        (i, j)
+    }
+
+    pub fn add_edge_by_index(&mut self, i: usize, j: usize, weight: usize) {
+        self.adj[i].push((j, weight));
+        if !self.directed && i != j {
+            self.adj[j].push((i, weight));
+        }
+    }
+
+    /// The `(neighbour, weight)` pairs of the edges leaving node `i`.
+    pub fn neighbors(&self, i: usize) -> &[(usize, usize)] {
+        &self.adj[i]
+    }
+
+    /// The weight of the first edge from `i` to `j`, if any.
+    pub fn weight(&self, i: usize, j: usize) -> Option<usize> {
+        self.adj[i].iter().find(|(k, _)| *k == j).map(|(_, w)| *w)
+    }
+
+    /// All edges as `(from, to, weight)`; undirected edges are listed once.
+    pub fn edges(&self) -> Vec<(usize, usize, usize)> {
+        let mut edges = Vec::new();
+        for (i, nei) in self.adj.iter().enumerate() {
+            for &(j, w) in nei {
+                if self.directed || i <= j {
+                    edges.push((i, j, w));
+                }
+            }
+        }
+        edges
+    }
+
+    /// Nodes that have an edge into `i`.
+    pub fn predecessors(&self, i: usize) -> Vec<usize> {
+        if !self.directed {
+            return self.adj[i].iter().map(|(j, _)| *j).collect();
+        }
+        (0..self.len())
+            .filter(|&j| self.adj[j].iter().any(|(k, _)| *k == i))
+            .collect()
+    }
+
+    /// Nodes reachable from `start` by following edges, `start` included.
+    pub fn reachable(&self, start: usize) -> Vec<bool> {
+        let mut seen = vec![false; self.len()];
+        let mut queue = VecDeque::from([start]);
+        seen[start] = true;
+        while let Some(i) = queue.pop_front() {
+            for &(j, _) in &self.adj[i] {
+                if !seen[j] {
+                    seen[j] = true;
+                    queue.push_back(j);
+                }
+            }
+        }
+        seen
+    }
+
+    /// The edges `(from, to, weight)` of a path from `start` to `end` with the
+    /// fewest edges, or `None` if `end` cannot be reached.
+    pub fn path(&self, start: usize, end: usize) -> Option<Vec<(usize, usize, usize)>> {
+        let mut previous: Vec<Option<(usize, usize)>> = vec![None; self.len()];
+        let mut queue = VecDeque::from([start]);
+        while let Some(i) = queue.pop_front() {
+            if i == end {
+                let mut path = Vec::new();
+                let mut j = end;
+                while j != start {
+                    let (i, w) = previous[j].unwrap();
+                    path.push((i, j, w));
+                    j = i;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for &(j, w) in &self.adj[i] {
+                if j != start && previous[j].is_none() {
+                    previous[j] = Some((i, w));
+                    queue.push_back(j);
+                }
+            }
+        }
+        None
+    }
+
+    /// Connected components, ignoring edge direction.
+    pub fn connected_components(&self) -> Vec<Vec<usize>> {
+        let mut undirected: Vec<Vec<usize>> = vec![Vec::new(); self.len()];
+        for (i, j, _) in self.edges() {
+            undirected[i].push(j);
+            undirected[j].push(i);
+        }
+        let mut component = vec![usize::MAX; self.len()];
+        let mut components = Vec::new();
+        for s in 0..self.len() {
+            if component[s] != usize::MAX {
+                continue;
+            }
+            let c = components.len();
+            let mut members = vec![s];
+            component[s] = c;
+            let mut k = 0;
+            while k < members.len() {
+                for &j in &undirected[members[k]] {
+                    if component[j] == usize::MAX {
+                        component[j] = c;
+                        members.push(j);
+                    }
+                }
+                k += 1;
+            }
+            components.push(members);
+        }
+        components
+    }
+
+    /// A topological order of the nodes, or `None` if the graph has a cycle.
+    pub fn topological_sort(&self) -> Option<Vec<usize>> {
+        assert!(self.directed, "Topological sort needs a directed graph!");
+        let mut indegree = vec![0usize; self.len()];
+        for (_, j, _) in self.edges() {
+            indegree[j] += 1;
+        }
+        let mut queue: VecDeque<usize> = (0..self.len()).filter(|&i| indegree[i] == 0).collect();
+        let mut order = Vec::with_capacity(self.len());
+        while let Some(i) = queue.pop_front() {
+            order.push(i);
+            for &(j, _) in &self.adj[i] {
+                indegree[j] -= 1;
+                if indegree[j] == 0 {
+                    queue.push_back(j);
+                }
+            }
+        }
+        if order.len() == self.len() {
+            Some(order)
+        } else {
+            None
+        }
+    }
+
+    /// The global minimum cut of an undirected graph (Stoer–Wagner), or `None`
+    /// if the graph has fewer than two nodes.
+    pub fn min_cut(&self) -> Option<MinCut> {
+        assert!(!self.directed, "Minimum cut needs an undirected graph!");
+        let n = self.len();
+        if n < 2 {
+            return None;
+        }
+        let mut adj: Vec<HashMap<usize, usize>> = vec![HashMap::new(); n];
+        for (i, j, w) in self.edges() {
+            if i != j {
+                *adj[i].entry(j).or_insert(0) += w;
+                *adj[j].entry(i).or_insert(0) += w;
+            }
+        }
+        let mut members: Vec<Vec<usize>> = (0..n).map(|i| vec![i]).collect();
+        let mut alive: Vec<usize> = (0..n).collect();
+        let mut best: Option<(usize, Vec<usize>)> = None;
+
+        while alive.len() > 1 {
+            let (s, t, cut_of_phase) = minimum_cut_phase(&adj, &alive, n);
+            if best.as_ref().is_none_or(|(w, _)| cut_of_phase < *w) {
+                best = Some((cut_of_phase, members[t].clone()));
+            }
+            let merged = std::mem::take(&mut members[t]);
+            members[s].extend(merged);
+            let t_adj = std::mem::take(&mut adj[t]);
+            for (v, w) in t_adj {
+                adj[v].remove(&t);
+                if v != s {
+                    *adj[s].entry(v).or_insert(0) += w;
+                    *adj[v].entry(s).or_insert(0) += w;
+                }
+            }
+            alive.retain(|&v| v != t);
+        }
+
+        let (weight, side) = best?;
+        let mut in_side = vec![false; n];
+        for &i in &side {
+            in_side[i] = true;
+        }
+        let edges = self
+            .edges()
+            .into_iter()
+            .filter(|&(i, j, _)| in_side[i] != in_side[j])
+            .map(|(i, j, _)| (i, j))
+            .collect();
+        let other = (0..n).filter(|&i| !in_side[i]).collect();
+        Some(MinCut {
+            weight,
+            edges,
+            partition: (side, other),
+        })
+    }
+}
+
+fn minimum_cut_phase(
+    adj: &[HashMap<usize, usize>],
+    alive: &[usize],
+    n: usize,
+) -> (usize, usize, usize) {
+    let mut in_a = vec![false; n];
+    let mut weight = vec![0usize; n];
+    let mut heap = BinaryHeap::from([(0usize, alive[0])]);
+    let (mut prev, mut last, mut last_weight) = (alive[0], alive[0], 0);
+    let mut added = 0;
+    while added < alive.len() {
+        let (w, v) = match heap.pop() {
+            Some((w, v)) if in_a[v] || w != weight[v] => continue,
+            Some(entry) => entry,
+            None => {
+                // The rest of the graph is not connected to A.
+                let v = *alive.iter().find(|&&v| !in_a[v]).unwrap();
+                (weight[v], v)
+            }
+        };
+        in_a[v] = true;
+        prev = last;
+        last = v;
+        last_weight = w;
+        added += 1;
+        for (&u, &wu) in &adj[v] {
+            if !in_a[u] {
+                weight[u] += wu;
+                heap.push((weight[u], u));
+            }
+        }
+    }
+    (prev, last, last_weight)
+}
+
+impl<N: Clone + Eq + Hash + Display> Graph<N> {
+    /// Renders the graph in Graphviz DOT format. Weights other than 1 are shown
+    /// as edge labels.
+    pub fn to_dot(&self) -> String {
+        let (kind, arrow) = if self.directed {
+            ("digraph", "->")
+        } else {
+            ("graph", "--")
+        };
+        let mut dot = format!("{} {{\n", kind);
+        for node in &self.nodes {
+            writeln!(dot, "    \"{}\";", node).unwrap();
+        }
+        for (i, j, w) in self.edges() {
+            write!(
+                dot,
+                "    \"{}\" {} \"{}\"",
+                self.nodes[i], arrow, self.nodes[j]
+            )
+            .unwrap();
+            if w != 1 {
+                write!(dot, " [label=\"{}\"]", w).unwrap();
+            }
+            dot.push_str(";\n");
+        }
+        dot.push('}');
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The example from Stoer and Wagner's paper, whose minimum cut of weight 4
+    // separates {3, 4, 7, 8} from {1, 2, 5, 6}.
+    fn stoer_wagner_example() -> Graph<u32> {
+        let mut graph = Graph::undirected();
+        for (a, b, w) in [
+            (1, 2, 2),
+            (1, 5, 3),
+            (2, 3, 3),
+            (2, 5, 2),
+            (2, 6, 2),
+            (3, 4, 4),
+            (3, 7, 2),
+            (4, 7, 2),
+            (4, 8, 2),
+            (5, 6, 3),
+            (6, 7, 1),
+            (7, 8, 3),
+        ] {
+            graph.add_edge(a, b, w);
+        }
+        graph
+    }
+
+    fn names(graph: &Graph<u32>, nodes: &[usize]) -> Vec<u32> {
+        let mut names: Vec<u32> = nodes.iter().map(|&i| *graph.node(i)).collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn min_cut_of_known_graph() {
+        let graph = stoer_wagner_example();
+        let cut = graph.min_cut().unwrap();
+        assert_eq!(cut.weight, 4);
+        let mut sides = [
+            names(&graph, &cut.partition.0),
+            names(&graph, &cut.partition.1),
+        ];
+        sides.sort();
+        assert_eq!(sides, [vec![1, 2, 5, 6], vec![3, 4, 7, 8]]);
+        let mut edges: Vec<(u32, u32)> = cut
+            .edges
+            .iter()
+            .map(|&(i, j)| {
+                let (a, b) = (*graph.node(i), *graph.node(j));
+                (a.min(b), a.max(b))
+            })
+            .collect();
+        edges.sort();
+        assert_eq!(edges, [(2, 3), (6, 7)]);
+    }
+
+    #[test]
+    fn min_cut_of_disconnected_and_tiny_graphs() {
+        let mut graph = Graph::undirected();
+        graph.add_edge("a", "b", 5);
+        graph.add_edge("c", "d", 5);
+        let cut = graph.min_cut().unwrap();
+        assert_eq!(cut.weight, 0);
+        assert!(cut.edges.is_empty());
+        assert_eq!(cut.partition.0.len() + cut.partition.1.len(), 4);
+
+        let mut single: Graph<&str> = Graph::undirected();
+        single.intern("a");
+        assert!(single.min_cut().is_none());
+    }
+
+    #[test]
+    fn topological_sort_orders_edges() {
+        let mut graph = Graph::directed();
+        for (a, b) in [("shirt", "tie"), ("tie", "jacket"), ("trousers", "shoes")] {
+            graph.add_edge(a, b, 1);
+        }
+        graph.add_edge("trousers", "jacket", 1);
+        let order = graph.topological_sort().unwrap();
+        let position = |node| {
+            order
+                .iter()
+                .position(|&i| i == graph.index_of(&node).unwrap())
+        };
+        for (i, j, _) in graph.edges() {
+            assert!(position(*graph.node(i)) < position(*graph.node(j)));
+        }
+        graph.add_edge("jacket", "shirt", 1);
+        assert_eq!(graph.topological_sort(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "directed graph")]
+    fn topological_sort_rejects_undirected_graphs() {
+        let mut graph = Graph::undirected();
+        graph.add_edge("a", "b", 1);
+        graph.topological_sort();
+    }
+
+    #[test]
+    fn components_ignore_direction() {
+        let mut graph = Graph::directed();
+        graph.add_edge(1, 2, 1);
+        graph.add_edge(3, 2, 1);
+        graph.add_edge(4, 5, 1);
+        graph.intern(6);
+        let components: Vec<Vec<u32>> = graph
+            .connected_components()
+            .iter()
+            .map(|c| names(&graph, c))
+            .collect();
+        assert_eq!(components, [vec![1, 2, 3], vec![4, 5], vec![6]]);
+    }
+
+    #[test]
+    fn predecessors_reachability_and_paths() {
+        let mut graph = Graph::directed();
+        graph.add_edge(1, 2, 10);
+        graph.add_edge(2, 3, 20);
+        graph.add_edge(1, 3, 30);
+        graph.add_edge(4, 1, 40);
+        let index = |n| graph.index_of(&n).unwrap();
+        assert_eq!(names(&graph, &graph.predecessors(index(3))), [1, 2]);
+        assert_eq!(
+            names(&graph, &graph.predecessors(index(4))),
+            Vec::<u32>::new()
+        );
+
+        let reachable = graph.reachable(index(2));
+        let reached: Vec<usize> = (0..graph.len()).filter(|&i| reachable[i]).collect();
+        assert_eq!(names(&graph, &reached), [2, 3]);
+
+        assert_eq!(
+            graph.path(index(4), index(3)),
+            Some(vec![(index(4), index(1), 40), (index(1), index(3), 30)])
+        );
+        assert_eq!(graph.path(index(3), index(1)), None);
+        assert_eq!(graph.path(index(2), index(2)), Some(Vec::new()));
+    }
+
+    #[test]
+    fn undirected_predecessors_are_neighbours() {
+        let graph = stoer_wagner_example();
+        let index = graph.index_of(&1).unwrap();
+        assert_eq!(names(&graph, &graph.predecessors(index)), [2, 5]);
+        assert_eq!(graph.weight(index, graph.index_of(&5).unwrap()), Some(3));
+    }
+
+    #[test]
+    fn dot_output() {
+        let mut graph = Graph::directed();
+        graph.add_edge("a", "b", 1);
+        graph.add_edge("b", "c", 2);
+        assert_eq!(
+            graph.to_dot(),
+            "digraph {\n    \"a\";\n    \"b\";\n    \"c\";\n    \"a\" -> \"b\";\n    \"b\" -> \"c\" [label=\"2\"];\n}"
+        );
+        let mut graph = Graph::undirected();
+        graph.add_edge("a", "b", 1);
+        assert_eq!(
+            graph.to_dot(),
+            "graph {\n    \"a\";\n    \"b\";\n    \"a\" -- \"b\";\n}"
+        );
+    }
+}
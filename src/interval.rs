@@ -0,0 +1,625 @@
+use num::PrimInt;
+use std::fmt::{self, Display};
+
+/// A non-empty closed interval `[start, end]` of integers.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub struct Interval<T> {
+    pub start: T,
+    pub end: T,
+}
+
+impl<T: PrimInt> Interval<T> {
+    pub fn new(start: T, end: T) -> Self {
+        assert!(start <= end, "Interval start is past its end!");
+        Interval { start, end }
+    }
+
+    /// The interval `[start, end)`, or `None` when it is empty.
+    pub fn half_open(start: T, end: T) -> Option<Self> {
+        if start < end {
+            Some(Interval {
+                start,
+                end: end - T::one(),
+            })
+        } else {
+            None
+        }
+    }
+
+    /// The interval of `len` values beginning at `start`, or `None` when it is
+    /// empty or does not fit in `T`.
+    pub fn with_len(start: T, len: T) -> Option<Self> {
+        if len <= T::zero() {
+            return None;
+        }
+        let end = start.checked_add(&(len - T::one()))?;
+        Some(Interval { start, end })
+    }
+
+    pub fn contains(self, value: T) -> bool {
+        self.start <= value && value <= self.end
+    }
+
+    pub fn intersects(self, other: &Self) -> bool {
+        self.start.max(other.start) <= self.end.min(other.end)
+    }
+
+    pub fn intersect(self, other: &Self) -> Option<Self> {
+        let start = self.start.max(other.start);
+        let end = self.end.min(other.end);
+        if start <= end {
+            Some(Interval { start, end })
+        } else {
+            None
+        }
+    }
+
+    /// True when the two intervals overlap or sit right next to each other,
+    /// i.e. their union is again an interval.
+    pub fn touches(self, other: &Self) -> bool {
+        let (first, second) = if self.start <= other.start {
+            (self, *other)
+        } else {
+            (*other, self)
+        };
+        first.end >= second.start || first.end + T::one() == second.start
+    }
+
+    /// Splits the interval into the values below `at` and the values from `at`
+    /// onwards.
+    pub fn split_at(self, at: T) -> (Option<Self>, Option<Self>) {
+        if at <= self.start {
+            (None, Some(self))
+        } else if at > self.end {
+            (Some(self), None)
+        } else {
+            (
+                Some(Interval {
+                    start: self.start,
+                    end: at - T::one(),
+                }),
+                Some(Interval {
+                    start: at,
+                    end: self.end,
+                }),
+            )
+        }
+    }
+
+    pub fn subtract(self, other: &Self) -> IntervalSet<T> {
+        IntervalSet::from(self).difference(&IntervalSet::from(*other))
+    }
+
+    /// Number of integers in the interval, or `None` when that does not fit
+    /// in a `u128`, which only happens for the full range of a 128-bit `T`.
+    pub fn size(self) -> Option<u128> {
+        distance(self.start, self.end).checked_add(1)
+    }
+}
+
+impl<T: Display> Display for Interval<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}, {}]", self.start, self.end)
+    }
+}
+
+/// A set of integers stored as sorted, disjoint and non-adjacent intervals.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Default)]
+pub struct IntervalSet<T> {
+    intervals: Vec<Interval<T>>,
+}
+
+impl<T: PrimInt> IntervalSet<T> {
+    pub fn new() -> Self {
+        IntervalSet {
+            intervals: Vec::new(),
+        }
+    }
+
+    pub fn intervals(&self) -> &[Interval<T>] {
+        &self.intervals
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Interval<T>> {
+        self.intervals.iter()
+    }
+
+    pub fn insert(&mut self, interval: Interval<T>) {
+        self.intervals.push(interval);
+        self.normalise();
+    }
+
+    pub fn contains(&self, value: T) -> bool {
+        let i = self.intervals.partition_point(|iv| iv.end < value);
+        i < self.intervals.len() && self.intervals[i].contains(value)
+    }
+
+    pub fn min(&self) -> Option<T> {
+        self.intervals.first().map(|iv| iv.start)
+    }
+
+    pub fn max(&self) -> Option<T> {
+        self.intervals.last().map(|iv| iv.end)
+    }
+
+    /// Number of integers in the set, or `None` when that does not fit in a
+    /// `u128`.
+    pub fn len(&self) -> Option<u128> {
+        self.intervals
+            .iter()
+            .try_fold(0u128, |n, iv| n.checked_add(iv.size()?))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        self.intervals
+            .iter()
+            .chain(other.intervals.iter())
+            .copied()
+            .collect()
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let mut intervals = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < self.intervals.len() && j < other.intervals.len() {
+            let (a, b) = (self.intervals[i], other.intervals[j]);
+            if let Some(iv) = a.intersect(&b) {
+                intervals.push(iv);
+            }
+            if a.end < b.end {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        IntervalSet { intervals }
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        let mut intervals = Vec::new();
+        let mut j = 0;
+        for &a in &self.intervals {
+            let mut rest = Some(a);
+            while j < other.intervals.len() && other.intervals[j].end < a.start {
+                j += 1;
+            }
+            let mut k = j;
+            while let Some(current) = rest {
+                if k == other.intervals.len() || other.intervals[k].start > current.end {
+                    intervals.push(current);
+                    break;
+                }
+                let b = other.intervals[k];
+                let (below, _) = current.split_at(b.start);
+                if let Some(below) = below {
+                    intervals.push(below);
+                }
+                rest = if b.end < current.end {
+                    Some(Interval {
+                        start: b.end + T::one(),
+                        end: current.end,
+                    })
+                } else {
+                    None
+                };
+                k += 1;
+            }
+        }
+        IntervalSet { intervals }
+    }
+
+    /// The `n` smallest values of the set.
+    pub fn lowest(&self, n: u128) -> Self {
+        let mut intervals = Vec::new();
+        let mut left = n;
+        for &iv in &self.intervals {
+            if left == 0 {
+                break;
+            }
+            match iv.size() {
+                Some(size) if size <= left => {
+                    intervals.push(iv);
+                    left -= size;
+                }
+                _ => {
+                    // Fewer than `size` values, so the end fits in `T`.
+                    let end = advanced(iv.start, left - 1).unwrap();
+                    intervals.push(Interval {
+                        start: iv.start,
+                        end,
+                    });
+                    left = 0;
+                }
+            }
+        }
+        IntervalSet { intervals }
+    }
+
+    /// The values of `bounds` that are not in the set.
+    pub fn complement_within(&self, bounds: Interval<T>) -> Self {
+        IntervalSet::from(bounds).difference(self)
+    }
+
+    fn normalise(&mut self) {
+        self.intervals.sort();
+        let mut merged: Vec<Interval<T>> = Vec::with_capacity(self.intervals.len());
+        for iv in self.intervals.drain(..) {
+            match merged.last_mut() {
+                Some(last) if last.touches(&iv) => last.end = last.end.max(iv.end),
+                _ => merged.push(iv),
+            }
+        }
+        self.intervals = merged;
+    }
+}
+
+impl<T: PrimInt> From<Interval<T>> for IntervalSet<T> {
+    fn from(interval: Interval<T>) -> Self {
+        IntervalSet {
+            intervals: vec![interval],
+        }
+    }
+}
+
+impl<T: PrimInt> FromIterator<Interval<T>> for IntervalSet<T> {
+    fn from_iter<I: IntoIterator<Item = Interval<T>>>(iter: I) -> Self {
+        let mut set = IntervalSet {
+            intervals: iter.into_iter().collect(),
+        };
+        set.normalise();
+        set
+    }
+}
+
+impl<'a, T> IntoIterator for &'a IntervalSet<T> {
+    type Item = &'a Interval<T>;
+    type IntoIter = std::slice::Iter<'a, Interval<T>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.intervals.iter()
+    }
+}
+
+impl<T: Display> Display for IntervalSet<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts = self
+            .intervals
+            .iter()
+            .map(|iv| iv.to_string())
+            .collect::<Vec<_>>();
+        write!(f, "{{{}}}", parts.join(", "))
+    }
+}
+
+// `end - start` for `start <= end`, which always fits in a `u128`.
+fn distance<T: PrimInt>(start: T, end: T) -> u128 {
+    match end.checked_sub(&start) {
+        Some(d) => d.to_u128().unwrap(),
+        // Only for signed `T`, with `start < 0 <= end`: count the values
+        // below 0 and those from 0 separately.
+        None => {
+            let below = (T::zero() - (start + T::one())).to_u128().unwrap() + 1;
+            end.to_u128().unwrap() + below
+        }
+    }
+}
+
+// `start + by`, or `None` if it does not fit in `T`. For signed `T`, `by` can
+// be more than `T::max_value()`, so it is added in steps that fit.
+fn advanced<T: PrimInt>(start: T, mut by: u128) -> Option<T> {
+    let step = T::max_value().to_u128().unwrap();
+    let mut value = start;
+    while by > 0 {
+        let k = by.min(step);
+        value = value.checked_add(&T::from(k)?)?;
+        by -= k;
+    }
+    Some(value)
+}
+
+/// A piecewise-linear map: every piece shifts its source interval so that it
+/// starts at `dest`. Values not covered by any piece are mapped to themselves.
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub struct PiecewiseMap<T> {
+    pieces: Vec<(Interval<T>, T)>,
+}
+
+impl<T: PrimInt + Display> PiecewiseMap<T> {
+    pub fn new() -> Self {
+        PiecewiseMap { pieces: Vec::new() }
+    }
+
+    pub fn pieces(&self) -> &[(Interval<T>, T)] {
+        &self.pieces
+    }
+
+    /// Adds a piece sending `source` to the interval starting at `dest`. The
+    /// sources of the pieces must not overlap and the shifted interval must
+    /// fit in `T`.
+    pub fn push(&mut self, source: Interval<T>, dest: T) -> Result<(), String> {
+        if moved(source.end, source.start, dest).is_none() {
+            return Err(format!("Piece {} -> {} overflows!", source, dest));
+        }
+        let i = self.pieces.partition_point(|(s, _)| s.start < source.start);
+        for (other, _) in self.pieces[i.saturating_sub(1)..].iter().take(2) {
+            if other.intersects(&source) {
+                return Err(format!("Piece {} overlaps {}!", source, other));
+            }
+        }
+        self.pieces.insert(i, (source, dest));
+        Ok(())
+    }
+
+    pub fn apply(&self, value: T) -> T {
+        let i = self.pieces.partition_point(|(s, _)| s.end < value);
+        match self.pieces.get(i) {
+            Some(&(source, dest)) if source.contains(value) => {
+                moved(value, source.start, dest).unwrap()
+            }
+            _ => value,
+        }
+    }
+
+    /// The image of `set`, merged back into disjoint intervals.
+    pub fn apply_set(&self, set: &IntervalSet<T>) -> IntervalSet<T> {
+        let mut image = Vec::new();
+        for (source, dest) in self.segments() {
+            for iv in set.intersection(&IntervalSet::from(source)).iter() {
+                image.push(shift(*iv, source.start, dest));
+            }
+        }
+        image.into_iter().collect()
+    }
+
+    /// The pieces together with the identity pieces filling the gaps between
+    /// them, covering every value of `T`.
+    fn segments(&self) -> Vec<(Interval<T>, T)> {
+        let mut segments = Vec::new();
+        let mut next = Some(T::min_value());
+        for &(source, dest) in &self.pieces {
+            if let Some(gap) = next.and_then(|start| Interval::half_open(start, source.start)) {
+                segments.push((gap, gap.start));
+            }
+            segments.push((source, dest));
+            next = source.end.checked_add(&T::one());
+        }
+        if let Some(start) = next {
+            segments.push((Interval::new(start, T::max_value()), start));
+        }
+        segments
+    }
+
+    /// The map sending `x` to `next.apply(self.apply(x))`.
+    pub fn then(&self, next: &Self) -> Self {
+        let mut pieces = Vec::new();
+        for (source, dest) in self.segments() {
+            let image = shift(source, source.start, dest);
+            for (next_source, next_dest) in next.segments() {
+                if let Some(iv) = image.intersect(&next_source) {
+                    let start = moved(iv.start, next_source.start, next_dest).unwrap();
+                    pieces.push((shift(iv, dest, source.start), start));
+                }
+            }
+        }
+        PiecewiseMap::simplified(pieces)
+    }
+
+    /// Every value that the map sends into `set`.
+    pub fn preimage(&self, set: &IntervalSet<T>) -> IntervalSet<T> {
+        let mut preimage = Vec::new();
+        for (source, dest) in self.segments() {
+            let image = IntervalSet::from(shift(source, source.start, dest));
+            for iv in set.intersection(&image).iter() {
+                preimage.push(shift(*iv, dest, source.start));
+            }
+        }
+        preimage.into_iter().collect()
+    }
+
+    /// The inverse map, or `None` when two values are sent to the same place.
+    pub fn inverse(&self) -> Option<Self> {
+        let mut pieces: Vec<(Interval<T>, T)> = self
+            .segments()
+            .into_iter()
+            .map(|(source, dest)| (shift(source, source.start, dest), source.start))
+            .collect();
+        pieces.sort();
+        if pieces.windows(2).any(|w| w[0].0.intersects(&w[1].0)) {
+            return None;
+        }
+        Some(PiecewiseMap::simplified(pieces))
+    }
+
+    // Builds a map from disjoint pieces sorted by source, dropping the identity
+    // pieces and merging neighbours that continue the same shift.
+    fn simplified(pieces: Vec<(Interval<T>, T)>) -> Self {
+        let mut merged: Vec<(Interval<T>, T)> = Vec::new();
+        for (source, dest) in pieces {
+            if source.start == dest {
+                continue;
+            }
+            if let Some((last, last_dest)) = merged.last_mut() {
+                let continues = last.end.checked_add(&T::one()) == Some(source.start)
+                    && shift(*last, last.start, *last_dest)
+                        .end
+                        .checked_add(&T::one())
+                        == Some(dest);
+                if continues {
+                    last.end = source.end;
+                    continue;
+                }
+            }
+            merged.push((source, dest));
+        }
+        PiecewiseMap { pieces: merged }
+    }
+}
+
+// Moves `iv` by the offset that takes `from` to `to`.
+fn shift<T: PrimInt>(iv: Interval<T>, from: T, to: T) -> Interval<T> {
+    Interval {
+        start: moved(iv.start, from, to).unwrap(),
+        end: moved(iv.end, from, to).unwrap(),
+    }
+}
+
+// `to + (x - from)` for `x >= from`, or `None` if it does not fit in `T`. For
+// signed `T`, `x - from` may not fit even when the result does; then `from` is
+// negative and, for the result to fit, so is `to`, and `to - from` cannot
+// overflow.
+fn moved<T: PrimInt>(x: T, from: T, to: T) -> Option<T> {
+    match x.checked_sub(&from) {
+        Some(d) => to.checked_add(&d),
+        None => to.checked_sub(&from)?.checked_add(&x),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(intervals: &[(i64, i64)]) -> IntervalSet<i64> {
+        intervals
+            .iter()
+            .map(|&(start, end)| Interval::new(start, end))
+            .collect()
+    }
+
+    #[test]
+    fn half_open_and_with_len() {
+        assert_eq!(Interval::half_open(3, 7), Some(Interval::new(3, 6)));
+        assert_eq!(Interval::half_open(3, 3), None);
+        assert_eq!(Interval::half_open(5, 3), None);
+        assert_eq!(Interval::with_len(250u8, 6), Some(Interval::new(250, 255)));
+        assert_eq!(Interval::with_len(250u8, 7), None);
+        assert_eq!(Interval::with_len(1, 0), None);
+    }
+
+    #[test]
+    fn split_at() {
+        let iv = Interval::new(3, 7);
+        assert_eq!(iv.split_at(3), (None, Some(iv)));
+        assert_eq!(iv.split_at(8), (Some(iv), None));
+        assert_eq!(
+            iv.split_at(5),
+            (Some(Interval::new(3, 4)), Some(Interval::new(5, 7)))
+        );
+    }
+
+    #[test]
+    fn size_of_wide_intervals() {
+        assert_eq!(Interval::new(-3i8, 4).size(), Some(8));
+        assert_eq!(Interval::new(i64::MIN, i64::MAX).size(), Some(1 << 64));
+        assert_eq!(Interval::new(i128::MIN, -1).size(), Some(1 << 127));
+        assert_eq!(
+            Interval::new(i128::MIN + 1, i128::MAX).size(),
+            Some(u128::MAX)
+        );
+        assert_eq!(Interval::new(i128::MIN, i128::MAX).size(), None);
+        assert_eq!(Interval::new(u128::MAX - 9, u128::MAX).size(), Some(10));
+        assert_eq!(Interval::new(0, u128::MAX).size(), None);
+        assert_eq!(set(&[(-5, -1), (10, 19)]).len(), Some(15));
+        let full = IntervalSet::from(Interval::new(0, u128::MAX));
+        assert_eq!(full.len(), None);
+        assert_eq!(
+            full.difference(&IntervalSet::from(Interval::new(7, 7)))
+                .len(),
+            Some(u128::MAX)
+        );
+    }
+
+    #[test]
+    fn lowest_values() {
+        let s = set(&[(-5, -3), (10, 20)]);
+        assert_eq!(s.lowest(0), IntervalSet::new());
+        assert_eq!(s.lowest(2), set(&[(-5, -4)]));
+        assert_eq!(s.lowest(3), set(&[(-5, -3)]));
+        assert_eq!(s.lowest(5), set(&[(-5, -3), (10, 11)]));
+        assert_eq!(s.lowest(100), s);
+        let all = IntervalSet::from(Interval::new(i8::MIN, i8::MAX));
+        assert_eq!(all.lowest(200).max(), Some(71));
+        assert_eq!(all.lowest(255).max(), Some(126));
+        assert_eq!(all.lowest(256), all);
+        let all = IntervalSet::from(Interval::new(i128::MIN, i128::MAX));
+        assert_eq!(all.lowest(u128::MAX).max(), Some(i128::MAX - 1));
+    }
+
+    #[test]
+    fn normalises_overlapping_and_adjacent_intervals() {
+        let s = set(&[(10, 12), (1, 3), (4, 5), (11, 20), (30, 30), (22, 25)]);
+        assert_eq!(s, set(&[(1, 5), (10, 20), (22, 25), (30, 30)]));
+        assert_eq!(s.intervals().len(), 4);
+        let mut s = set(&[(1, 3), (5, 7)]);
+        s.insert(Interval::new(4, 4));
+        assert_eq!(s.intervals(), &[Interval::new(1, 7)]);
+        let edges = set(&[(i64::MIN, 0), (1, i64::MAX)]);
+        assert_eq!(edges.intervals(), &[Interval::new(i64::MIN, i64::MAX)]);
+    }
+
+    #[test]
+    fn union_intersection_difference() {
+        let a = set(&[(1, 5), (10, 20), (30, 40)]);
+        let b = set(&[(4, 12), (15, 16), (41, 50)]);
+        assert_eq!(a.union(&b), set(&[(1, 20), (30, 50)]));
+        assert_eq!(a.intersection(&b), set(&[(4, 5), (10, 12), (15, 16)]));
+        assert_eq!(
+            a.difference(&b),
+            set(&[(1, 3), (13, 14), (17, 20), (30, 40)])
+        );
+        assert_eq!(b.difference(&a), set(&[(6, 9), (41, 50)]));
+        assert!(a.intersection(&IntervalSet::new()).is_empty());
+        assert_eq!(a.difference(&IntervalSet::new()), a);
+        assert!(a.difference(&a).is_empty());
+    }
+
+    #[test]
+    fn subtract_and_complement() {
+        let iv = Interval::new(1, 10);
+        assert_eq!(iv.subtract(&Interval::new(4, 6)), set(&[(1, 3), (7, 10)]));
+        assert_eq!(iv.subtract(&Interval::new(-5, 0)), set(&[(1, 10)]));
+        assert!(iv.subtract(&Interval::new(0, 11)).is_empty());
+        let s = set(&[(3, 4), (8, 12)]);
+        assert_eq!(s.complement_within(iv), set(&[(1, 2), (5, 7)]));
+        let full = IntervalSet::from(Interval::new(i64::MIN, i64::MAX));
+        let rest = s.complement_within(Interval::new(i64::MIN, i64::MAX));
+        assert_eq!(rest.union(&s), full);
+        assert!(rest.intersection(&s).is_empty());
+    }
+
+    #[test]
+    fn signed_map_with_identity_gaps() {
+        let mut map = PiecewiseMap::<i64>::new();
+        map.push(Interval::new(10, 20), 100).unwrap();
+        let set = IntervalSet::from(Interval::new(-5, 15));
+        let image: IntervalSet<i64> = [Interval::new(-5, 9), Interval::new(100, 105)]
+            .into_iter()
+            .collect();
+        assert_eq!(map.apply_set(&set), image);
+        assert_eq!(map.preimage(&image), set.union(&image));
+        assert_eq!(map.apply(-5), -5);
+        assert_eq!(map.apply(15), 105);
+
+        assert_eq!(map.inverse(), None);
+        map.push(Interval::new(100, 110), 10).unwrap();
+        assert_eq!(map.inverse().as_ref(), Some(&map));
+        assert!(map.then(&map).pieces().is_empty());
+    }
+
+    #[test]
+    fn signed_pieces_spanning_the_whole_range() {
+        let mut map = PiecewiseMap::<i8>::new();
+        assert!(map.push(Interval::new(-128, 127), -127).is_err());
+        map.push(Interval::new(-128, 0), -1).unwrap();
+        assert_eq!(map.apply(-128), -1);
+        assert_eq!(map.apply(0), 127);
+        map.push(Interval::new(1, 127), -128).unwrap();
+        assert_eq!(map.apply(127), -2);
+        assert_eq!(map.inverse().unwrap().apply(127), 0);
+        let all = IntervalSet::from(Interval::new(i8::MIN, i8::MAX));
+        assert_eq!(map.apply_set(&all), all);
+    }
+}
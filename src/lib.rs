@@ -1,17 +1,55 @@
 use core::fmt;
+use rand::{rngs::StdRng, SeedableRng};
+use std::collections::HashMap;
 use std::fmt::{Debug, Display};
 use std::hash::{Hash, Hasher};
 use std::iter::{DoubleEndedIterator, ExactSizeIterator, Iterator};
 use std::ops::{Index, IndexMut};
 
+pub mod graph;
+pub mod interval;
+pub mod matcher;
+pub mod parse;
+
+/// Options given to the runner that every puzzle may pick up.
+#[derive(Debug, Clone, Default)]
+pub struct Context {
+    pub seed: Option<u64>,
+    pub details: bool,
+    pub params: HashMap<String, String>,
+}
+
+impl Context {
+    /// A random number generator, seeded from `--seed` when one was given.
+    pub fn rng(&self) -> StdRng {
+        match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        }
+    }
+
+    /// The value given with `--param name=value`, if any.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params.get(name).map(|s| s.as_str())
+    }
+}
+
 pub trait Puzzle {
+    fn configure(&mut self, _context: &Context) {}
+
     fn load_input(&mut self);
 
     fn part1(&self) -> String;
 
     fn part2(&self) -> String;
 
-    fn run(&mut self) -> (String, String) {
+    /// Extra lines printed after the answers.
+    fn details(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn run(&mut self, context: &Context) -> (String, String) {
+        self.configure(context);
         self.load_input();
         (self.part1(), self.part2())
     }
@@ -58,14 +58,30 @@ enum Selector {
     Single(usize),
 }
 
+fn run_day(n: usize, day: &mut dyn Puzzle, context: &lib::Context) {
+    lib::print_day(2023, n, day.run(context));
+    for line in day.details() {
+        println!("{}", line);
+    }
+}
+
 fn main() {
     let mut args = std::env::args().skip(1);
 
-    let selection = if args.len() == 0 {
-        Selector::All
-    } else {
-        Selector::Single(args.next().unwrap().parse::<usize>().unwrap())
-    };
+    let mut selection = Selector::All;
+    let mut context = lib::Context::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => context.seed = Some(args.next().unwrap().parse::<u64>().unwrap()),
+            "--details" => context.details = true,
+            "--param" => {
+                let param = args.next().unwrap();
+                let (name, value) = param.split_once('=').unwrap();
+                context.params.insert(name.to_string(), value.to_string());
+            }
+            day => selection = Selector::Single(day.parse::<usize>().unwrap()),
+        }
+    }
 
     let mut day1 = Day1::new();
     let mut day2 = Day2::new();
@@ -96,15 +112,16 @@ fn main() {
     let mut days: Vec<&mut dyn Puzzle> = vec![
         &mut day1, &mut day2, &mut day3, &mut day4, &mut day5, &mut day6, &mut day7, &mut day8,
         &mut day9, &mut day10, &mut day11, &mut day12, &mut day13, &mut day14, &mut day15,
-        &mut day16, &mut day17, &mut day18, &mut day19, &mut day20, &mut day21, &mut day22, &mut day23, &mut day24, &mut day25
+        &mut day16, &mut day17, &mut day18, &mut day19, &mut day20, &mut day21, &mut day22,
+        &mut day23, &mut day24, &mut day25,
     ];
 
     match selection {
-        Selector::Single(n) => lib::print_day(2023, n, days[n - 1].run()),
+        Selector::Single(n) => run_day(n, days[n - 1], &context),
         Selector::All => {
             days.into_iter()
                 .enumerate()
-                .for_each(|(n, day)| lib::print_day(2023, n + 1, day.run()));
+                .for_each(|(n, day)| run_day(n + 1, day, &context));
         }
     }
 }
@@ -0,0 +1,186 @@
+use std::collections::{HashMap, VecDeque};
+
+/// Multi-pattern string search (Aho–Corasick). Every pattern carries a value
+/// and all occurrences are reported in a single pass, overlapping ones
+/// included.
+#[derive(Debug, Clone)]
+pub struct Matcher<V> {
+    patterns: Vec<(String, V)>,
+    next: Vec<HashMap<u8, usize>>,
+    fail: Vec<usize>,
+    out: Vec<Vec<usize>>,
+}
+
+/// An occurrence of a pattern at byte positions `start..end`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Match<'a, V> {
+    pub start: usize,
+    pub end: usize,
+    pub pattern: &'a str,
+    pub value: &'a V,
+}
+
+impl<V> Matcher<V> {
+    pub fn new<S: Into<String>, I: IntoIterator<Item = (S, V)>>(patterns: I) -> Self {
+        let mut matcher = Matcher {
+            patterns: Vec::new(),
+            next: vec![HashMap::new()],
+            fail: vec![0],
+            out: vec![Vec::new()],
+        };
+        for (pattern, value) in patterns {
+            let pattern = pattern.into();
+            assert!(!pattern.is_empty(), "Cannot match an empty pattern!");
+            let mut state = 0;
+            for &b in pattern.as_bytes() {
+                state = match matcher.next[state].get(&b) {
+                    Some(&s) => s,
+                    None => {
+                        let s = matcher.next.len();
+                        matcher.next.push(HashMap::new());
+                        matcher.fail.push(0);
+                        matcher.out.push(Vec::new());
+                        matcher.next[state].insert(b, s);
+                        s
+                    }
+                };
+            }
+            matcher.out[state].push(matcher.patterns.len());
+            matcher.patterns.push((pattern, value));
+        }
+
+        let mut queue: VecDeque<usize> = matcher.next[0].values().copied().collect();
+        while let Some(state) = queue.pop_front() {
+            let edges: Vec<(u8, usize)> =
+                matcher.next[state].iter().map(|(&b, &s)| (b, s)).collect();
+            for (b, child) in edges {
+                let fail = matcher.step(matcher.fail[state], b);
+                matcher.fail[child] = fail;
+                let inherited = matcher.out[fail].clone();
+                matcher.out[child].extend(inherited);
+                queue.push_back(child);
+            }
+        }
+        matcher
+    }
+
+    pub fn patterns(&self) -> &[(String, V)] {
+        &self.patterns
+    }
+
+    /// A matcher for the same values with every pattern spelled backwards.
+    pub fn reversed(&self) -> Self
+    where
+        V: Clone,
+    {
+        Matcher::new(
+            self.patterns
+                .iter()
+                .map(|(p, v)| (p.chars().rev().collect::<String>(), v.clone())),
+        )
+    }
+
+    fn step(&self, mut state: usize, b: u8) -> usize {
+        loop {
+            if let Some(&s) = self.next[state].get(&b) {
+                return s;
+            }
+            if state == 0 {
+                return 0;
+            }
+            state = self.fail[state];
+        }
+    }
+
+    /// All occurrences in `haystack`, ordered by end position and, for equal
+    /// ends, longest first.
+    pub fn find_all<'a>(&'a self, haystack: &str) -> Vec<Match<'a, V>> {
+        let mut matches = Vec::new();
+        let mut state = 0;
+        for (i, &b) in haystack.as_bytes().iter().enumerate() {
+            state = self.step(state, b);
+            for &p in &self.out[state] {
+                let (pattern, value) = &self.patterns[p];
+                matches.push(Match {
+                    start: i + 1 - pattern.len(),
+                    end: i + 1,
+                    pattern,
+                    value,
+                });
+            }
+        }
+        matches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbers() -> Matcher<usize> {
+        Matcher::new([
+            ("one", 1),
+            ("two", 2),
+            ("three", 3),
+            ("eight", 8),
+            ("ne", 10),
+            ("1", 1),
+        ])
+    }
+
+    fn found<'a>(matches: &[Match<'a, usize>]) -> Vec<(usize, &'a str)> {
+        matches.iter().map(|m| (m.start, m.pattern)).collect()
+    }
+
+    #[test]
+    fn overlapping_matches() {
+        let m = numbers();
+        assert_eq!(
+            found(&m.find_all("twone")),
+            [(0, "two"), (2, "one"), (3, "ne")]
+        );
+        assert_eq!(found(&m.find_all("eightwo")), [(0, "eight"), (4, "two")]);
+        let values: Vec<usize> = m.find_all("xtwonex1").iter().map(|m| *m.value).collect();
+        assert_eq!(values, [2, 1, 10, 1]);
+    }
+
+    #[test]
+    fn nested_patterns_end_together() {
+        let m = numbers();
+        let matches = m.find_all("one");
+        assert_eq!(found(&matches), [(0, "one"), (1, "ne")]);
+        assert_eq!((matches[0].end, matches[1].end), (3, 3));
+        assert_eq!(found(&m.find_all("nne")), [(1, "ne")]);
+    }
+
+    #[test]
+    fn matches_are_ordered_by_end_then_longest_first() {
+        let m = Matcher::new([("b", 0), ("abc", 1), ("bc", 2), ("c", 3), ("cd", 4)]);
+        let matches = m.find_all("abcd");
+        let order: Vec<(usize, usize)> = matches.iter().map(|m| (m.start, m.end)).collect();
+        assert_eq!(order, [(1, 2), (0, 3), (1, 3), (2, 3), (2, 4)]);
+    }
+
+    #[test]
+    fn no_match() {
+        let m = numbers();
+        assert!(m.find_all("").is_empty());
+        assert!(m.find_all("tw0 thre n e").is_empty());
+    }
+
+    #[test]
+    fn reversed_patterns() {
+        let m = numbers().reversed();
+        assert_eq!(
+            found(&m.find_all("owtxeno")),
+            [(0, "owt"), (4, "en"), (4, "eno")]
+        );
+        assert!(m.find_all("two").is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot match an empty pattern!")]
+    fn empty_pattern() {
+        Matcher::new([("", 0)]);
+    }
+}
@@ -0,0 +1,283 @@
+use nom::{
+    branch::alt,
+    character::complete::{char, digit1, multispace0, space0, space1},
+    combinator::{all_consuming, map_res, opt, recognize},
+    error::ParseError as NomParseError,
+    multi::separated_list1,
+    sequence::{delimited, pair, separated_pair, tuple},
+    IResult, Parser,
+};
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+/// A parse failure, located by line and column (both 1-based) in the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "parse error at line {}, column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl ParseError {
+    /// Builds an error pointing at `rest`, which must be a suffix of `input`.
+    pub fn at(input: &str, rest: &str, message: impl Into<String>) -> Self {
+        let consumed = &input[..input.len() - rest.len()];
+        let line = consumed.matches('\n').count() + 1;
+        let column = consumed.len() - consumed.rfind('\n').map_or(0, |i| i + 1) + 1;
+        ParseError {
+            line,
+            column,
+            message: message.into(),
+        }
+    }
+
+    fn from_nom(input: &str, err: nom::Err<nom::error::Error<&str>>) -> Self {
+        match err {
+            nom::Err::Error(e) | nom::Err::Failure(e) => {
+                let snippet = e.input.lines().next().unwrap_or("");
+                ParseError::at(
+                    input,
+                    e.input,
+                    format!("{:?} failed at {:?}", e.code, snippet),
+                )
+            }
+            nom::Err::Incomplete(_) => ParseError::at(input, "", "incomplete input"),
+        }
+    }
+
+    /// Shifts the error down by `offset` lines, for errors raised while
+    /// parsing a single line or block of a larger input.
+    pub fn offset_lines(mut self, offset: usize) -> Self {
+        self.line += offset;
+        self
+    }
+}
+
+/// Runs `parser` over the whole of `input`, ignoring surrounding whitespace.
+pub fn parse_all<'a, O, F>(input: &'a str, parser: F) -> Result<O, ParseError>
+where
+    F: Parser<&'a str, O, nom::error::Error<&'a str>>,
+{
+    all_consuming(ws(parser))(input)
+        .map(|(_, o)| o)
+        .map_err(|e| ParseError::from_nom(input, e))
+}
+
+/// Runs `parser` on every line of `input`; each line must be consumed entirely.
+pub fn parse_lines<'a, O, F>(input: &'a str, mut parser: F) -> Result<Vec<O>, ParseError>
+where
+    F: Parser<&'a str, O, nom::error::Error<&'a str>>,
+{
+    input
+        .lines()
+        .enumerate()
+        .map(|(i, line)| {
+            all_consuming(|s| parser.parse(s))(line)
+                .map(|(_, o)| o)
+                .map_err(|e| ParseError::from_nom(line, e).offset_lines(i))
+        })
+        .collect()
+}
+
+/// Splits `input` into blocks separated by one or more blank lines, keeping
+/// the line number at which each block starts (0-based).
+pub fn blocks(input: &str) -> Vec<(usize, &str)> {
+    let mut result = Vec::new();
+    let mut start: Option<(usize, usize)> = None;
+    let mut offset = 0;
+    let mut end = 0;
+    for (i, line) in input.split_inclusive('\n').enumerate() {
+        if line.trim().is_empty() {
+            if let Some((n, s)) = start.take() {
+                result.push((n, input[s..end].trim_end_matches(['\n', '\r'])));
+            }
+        } else {
+            if start.is_none() {
+                start = Some((i, offset));
+            }
+            end = offset + line.len();
+        }
+        offset += line.len();
+    }
+    if let Some((n, s)) = start {
+        result.push((n, input[s..end].trim_end_matches(['\n', '\r'])));
+    }
+    result
+}
+
+/// Runs `parser` on every blank-line-separated block of `input`.
+pub fn parse_blocks<'a, O, F>(input: &'a str, mut parser: F) -> Result<Vec<O>, ParseError>
+where
+    F: Parser<&'a str, O, nom::error::Error<&'a str>>,
+{
+    blocks(input)
+        .into_iter()
+        .map(|(n, block)| {
+            all_consuming(|s| parser.parse(s))(block)
+                .map(|(_, o)| o)
+                .map_err(|e| ParseError::from_nom(block, e).offset_lines(n))
+        })
+        .collect()
+}
+
+pub fn ws<'a, F, O, E: NomParseError<&'a str>>(
+    inner: F,
+) -> impl FnMut(&'a str) -> IResult<&'a str, O, E>
+where
+    F: Parser<&'a str, O, E>,
+{
+    delimited(multispace0, inner, multispace0)
+}
+
+pub fn unsigned<T: FromStr>(s: &str) -> IResult<&str, T> {
+    map_res(digit1, |d: &str| d.parse::<T>())(s)
+}
+
+pub fn signed<T: FromStr>(s: &str) -> IResult<&str, T> {
+    map_res(
+        recognize(pair(opt(alt((char('-'), char('+')))), digit1)),
+        |d: &str| d.parse::<T>(),
+    )(s)
+}
+
+/// A list of items separated by spaces or tabs, e.g. `0 3 6 9 12`.
+pub fn spaced<'a, F, O>(inner: F) -> impl FnMut(&'a str) -> IResult<&'a str, Vec<O>>
+where
+    F: Parser<&'a str, O, nom::error::Error<&'a str>>,
+{
+    separated_list1(space1, inner)
+}
+
+/// A list of items separated by commas, optionally followed by spaces, e.g.
+/// `1,1,3` or `a, b, c`.
+pub fn comma_separated<'a, F, O>(inner: F) -> impl FnMut(&'a str) -> IResult<&'a str, Vec<O>>
+where
+    F: Parser<&'a str, O, nom::error::Error<&'a str>>,
+{
+    separated_list1(pair(char(','), space0), inner)
+}
+
+/// A comma-separated triple, e.g. `1,0,1` or `19, 13, 30`.
+pub fn triple<'a, F, O>(mut inner: F) -> impl FnMut(&'a str) -> IResult<&'a str, (O, O, O)>
+where
+    F: Parser<&'a str, O, nom::error::Error<&'a str>>,
+{
+    move |s| {
+        let (s, a) = inner.parse(s)?;
+        let (s, _) = pair(char(','), space0)(s)?;
+        let (s, b) = inner.parse(s)?;
+        let (s, _) = pair(char(','), space0)(s)?;
+        let (s, c) = inner.parse(s)?;
+        Ok((s, (a, b, c)))
+    }
+}
+
+/// A `key: value` line.
+pub fn key_value<'a, K, V, OK, OV>(
+    key: K,
+    value: V,
+) -> impl FnMut(&'a str) -> IResult<&'a str, (OK, OV)>
+where
+    K: Parser<&'a str, OK, nom::error::Error<&'a str>>,
+    V: Parser<&'a str, OV, nom::error::Error<&'a str>>,
+{
+    separated_pair(key, tuple((space0, char(':'), space0)), value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nom::bytes::complete::tag;
+    use nom::character::complete::{alpha1, multispace1, newline};
+
+    #[test]
+    fn blocks_skip_blank_lines() {
+        let input = "\n\na\nb\n\n \n\t\nc\n\n";
+        assert_eq!(blocks(input), vec![(2, "a\nb"), (7, "c")]);
+        assert_eq!(blocks("a\r\n\r\nb\r\n"), vec![(0, "a"), (2, "b")]);
+        assert_eq!(blocks("only"), vec![(0, "only")]);
+        assert!(blocks("\n\n").is_empty());
+    }
+
+    #[test]
+    fn errors_point_at_line_and_column() {
+        let err = ParseError::at("ab\ncd", "d", "oops");
+        assert_eq!((err.line, err.column), (2, 2));
+        let err = err.offset_lines(10);
+        assert_eq!((err.line, err.column), (12, 2));
+        assert_eq!(err.to_string(), "parse error at line 12, column 2: oops");
+    }
+
+    #[test]
+    fn parse_lines_reports_the_failing_line() {
+        let parsed = parse_lines("1 2\n3 4\n", spaced(unsigned::<u32>));
+        assert_eq!(parsed, Ok(vec![vec![1, 2], vec![3, 4]]));
+        let err = parse_lines("1 2\n3 4\n5 x\n", spaced(unsigned::<u32>)).unwrap_err();
+        assert_eq!((err.line, err.column), (3, 2));
+    }
+
+    #[test]
+    fn parse_blocks_reports_the_line_in_the_whole_input() {
+        let numbers = |s| separated_list1(newline, unsigned::<u32>)(s);
+        let parsed = parse_blocks("1\n2\n\n\n3\n", numbers);
+        assert_eq!(parsed, Ok(vec![vec![1, 2], vec![3]]));
+        let err = parse_blocks("1\n2\n\n\n3\n45x\n", numbers).unwrap_err();
+        assert_eq!((err.line, err.column), (6, 3));
+    }
+
+    #[test]
+    fn parse_all_reports_line_and_column() {
+        let numbers = |s| separated_list1(multispace1, unsigned::<u32>)(s);
+        assert_eq!(parse_all("\n 1\n2 3\n\n", numbers), Ok(vec![1, 2, 3]));
+        let err = parse_all("1\n2\n 3 ?\n", numbers).unwrap_err();
+        assert_eq!((err.line, err.column), (3, 4));
+    }
+
+    #[test]
+    fn signed_numbers() {
+        assert_eq!(signed::<i32>("+5 "), Ok((" ", 5)));
+        assert_eq!(signed::<i32>("-5"), Ok(("", -5)));
+        assert_eq!(signed::<i32>("7"), Ok(("", 7)));
+        assert!(signed::<i32>("--5").is_err());
+        assert!(signed::<i32>("- 5").is_err());
+        assert!(signed::<u8>("-1").is_err());
+    }
+
+    #[test]
+    fn lists() {
+        assert_eq!(
+            spaced(unsigned::<u32>)("0  3\t6 x"),
+            Ok((" x", vec![0, 3, 6]))
+        );
+        assert_eq!(
+            comma_separated(unsigned::<u32>)("1,1,3"),
+            Ok(("", vec![1, 1, 3]))
+        );
+        assert_eq!(
+            comma_separated(alpha1)("a, b,c"),
+            Ok(("", vec!["a", "b", "c"]))
+        );
+        assert_eq!(triple(signed::<i64>)("19, -13,30"), Ok(("", (19, -13, 30))));
+        assert!(triple(signed::<i64>)("1,0").is_err());
+    }
+
+    #[test]
+    fn key_values() {
+        let mut line = key_value(tag("Time"), spaced(unsigned::<u32>));
+        assert_eq!(line("Time:   7 15"), Ok(("", ("Time", vec![7, 15]))));
+        assert_eq!(line("Time : 7"), Ok(("", ("Time", vec![7]))));
+        assert!(line("Time 7").is_err());
+    }
+}